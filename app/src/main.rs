@@ -1,93 +1,243 @@
-use pico_sdk::io::{commit, read_as, read_vec};
-use tree_hash::TreeHash;
+use pico_sdk::io::{commit, read_vec};
+use tree_hash::{Hash256, TreeHash};
 
 use ream_consensus::{
     attestation::Attestation,
+    attestation_data::AttestationData,
     attester_slashing::AttesterSlashing,
+    beacon_block_header::BeaconBlockHeader,
     bls_to_execution_change::SignedBLSToExecutionChange,
+    checkpoint::Checkpoint,
     electra::{
-        beacon_block::BeaconBlock,
+        beacon_block::{BeaconBlock, SignedBeaconBlock},
+        beacon_block_body::BeaconBlockBody,
         beacon_state::BeaconState,
         execution_payload::ExecutionPayload,
     },
     deposit::Deposit,
+    deposit_data::DepositData,
+    eth_1_data::Eth1Data,
     proposer_slashing::ProposerSlashing,
     sync_aggregate::SyncAggregate,
+    sync_committee::SyncCommittee,
+    validator::Validator,
     voluntary_exit::SignedVoluntaryExit,
 };
 use ream_lib::{
-    input::{OperationInput, EpochProcessingType},
-    ssz::from_ssz_bytes,
+    frame::{
+        decode_inputs, Decoding, Fork, InputHeader, Merkleization, Phase, Preset,
+        SignatureVerification, StateInput, Transport,
+    },
+    fork_choice::Store,
+    fulu::{FuluBeaconState, ProposerDivergence},
+    input::{OperationInput, EpochProcessingType, OperationKind},
+    light_client::{is_better_update, LightClientBootstrap, LightClientStore, LightClientUpdate},
+    merkle::StateRootCache,
+    merkle_proof::is_valid_gindex_branch,
+    output::{GuestOutput, InputStage},
+    rewards::{compute_deltas, computed_deltas_digest},
+    shuffling::{compute_mapping, mapping_digest},
+    snappy::decode_snappy,
+    spec::SLOTS_PER_EPOCH,
+    ssz::{from_ssz_bytes, LazyBeaconState},
+    ssz_static::SszType,
+    state_query::{query_validators, QueryAnswer, ValidatorField},
+    witness::{BlsToExecutionChangeWitness, ProcessSlotWitness},
 };
 
-fn deserialize<T: ssz::Decode>(ssz_bytes: &[u8]) -> T {
+fn deserialize<T: ssz::Decode>(ssz_bytes: &[u8], stage: InputStage) -> Result<T, InputStage> {
     // eprintln!("{}-{}:{}: {}", "deserialize", std::any::type_name::<T>(), "start", env::cycle_count());
-    let deserialized = from_ssz_bytes(&ssz_bytes).unwrap();
+    let deserialized = from_ssz_bytes(ssz_bytes).map_err(|_| stage);
     // eprintln!("{}-{}:{}: {}", "deserialize", std::any::type_name::<T>(), "end", env::cycle_count());
 
     deserialized
 }
 
 fn main() {
-    // Read inputs to the program.
+    // Malformed inputs are committed as a rejection instead of aborting the emulation, so the
+    // host always receives verifiable public values.
+    let output = run().unwrap_or_else(GuestOutput::InvalidInput);
+
+    // eprintln!("{}:{}: {}", "commit", "start", env::cycle_count());
+    commit(&output);
+    // eprintln!("{}:{}: {}", "commit", "end", env::cycle_count());
+}
+
+fn run() -> Result<GuestOutput, InputStage> {
+    // Read inputs to the program. See `ream_lib::frame` for the layout of the stdin buffers.
+    let header = InputHeader::from_bytes(&read_vec()).ok_or(InputStage::ReadHeader)?;
+    // This guest is built for mainnet states only, and only decodes Fulu states eagerly and in full
+    let partial_view = header.state_input != StateInput::Full || header.decoding != Decoding::Eager;
+    if header.preset != Preset::Mainnet || (header.fork == Fork::Fulu && partial_view) {
+        return Err(InputStage::ReadHeader);
+    }
+    // ream's `process_*` functions verify BLS signatures unconditionally; with pairings off every
+    // signature check passes without computing them. See `bls-switch`.
+    bls12_381::set_pairings(header.signatures == SignatureVerification::Required);
 
     // eprintln!("{}:{}: {}", "read-pre-state-ssz", "start", env::cycle_count());
-    let pre_state_len: usize = read_as();
-    let mut pre_state_ssz_bytes = vec![0u8; pre_state_len];
-    pre_state_ssz_bytes=read_vec();
-    // env::read_slice(&mut pre_state_ssz_bytes);
+    let pre_state_bytes = read_vec();
     // eprintln!("{}:{}: {}", "read-pre-state-ssz", "end", env::cycle_count());
-    
-    let mut state: BeaconState = deserialize(&pre_state_ssz_bytes);
+
+    // eprintln!("{}:{}: {}", "decompress-pre-state-ssz", "start", env::cycle_count());
+    let pre_state_ssz_bytes = match header.transport {
+        Transport::Raw => pre_state_bytes,
+        Transport::Snappy => {
+            decode_snappy(&pre_state_bytes).map_err(|_| InputStage::DecompressPreState)?
+        }
+    };
+    // eprintln!("{}:{}: {}", "decompress-pre-state-ssz", "end", env::cycle_count());
+
+    if header.state_input == StateInput::Witness {
+        return run_witness(&header, &pre_state_ssz_bytes, read_vec());
+    }
+    if header.decoding == Decoding::Lazy {
+        return run_lazy(&header, &pre_state_ssz_bytes);
+    }
+
     // eprintln!("{}:{}: {}", "read-operation-input", "start", env::cycle_count());
-    let input: OperationInput = read_as::<OperationInput>();
+    let payload = read_vec();
     // eprintln!("{}:{}: {}", "read-operation-input", "end", env::cycle_count());
+    let cached_roots = read_vec();
+    if header.stop_after == Phase::Read {
+        return Ok(GuestOutput::Stopped(Phase::Read));
+    }
+
+    let inputs: Vec<OperationInput> =
+        decode_inputs(&header, payload).ok_or(InputStage::ReadOperationInput)?;
+    // ssz_static cases decode and hash a standalone container, without any state
+    if let [OperationInput::SszStatic(ssz_type, ssz_bytes)] = inputs.as_slice() {
+        return ssz_static_root(*ssz_type, ssz_bytes);
+    }
+    // Shuffling cases only depend on their seed and count
+    if let [OperationInput::Shuffle { seed, count }] = inputs.as_slice() {
+        return shuffle(*seed, *count);
+    }
+    // Single Merkle proof cases open a branch into the object in the pre-state buffer
+    if let [OperationInput::MerkleProof { ssz_type, leaf, gindex, branch }] = inputs.as_slice() {
+        return verify_merkle_proof(*ssz_type, &pre_state_ssz_bytes, *leaf, *gindex, branch);
+    }
+    // Fork choice cases run their steps against a store anchored at the pre-state
+    if let [OperationInput::ForkChoiceAnchor(_), ..] = inputs.as_slice() {
+        return run_fork_choice(&pre_state_ssz_bytes, inputs);
+    }
+    // Light client sync cases run against a store initialized from the bootstrap in the pre-state
+    // buffer, and update ranking cases only compare their updates
+    if let [OperationInput::LightClientBootstrap { .. }, ..] = inputs.as_slice() {
+        return run_light_client_sync(&pre_state_ssz_bytes, inputs);
+    }
+    if let [OperationInput::RankedLightClientUpdate(_), ..] = inputs.as_slice() {
+        return rank_light_client_updates(inputs);
+    }
+    // Upgrades start from a state of the previous fork, which is not an Electra `BeaconState`
+    if inputs.iter().any(OperationInput::needs_previous_fork_state) {
+        return run_upgrade(&header, &pre_state_ssz_bytes, inputs);
+    }
+    // Fulu states carry the proposer lookahead, which ream's Electra `BeaconState` lacks
+    if header.fork == Fork::Fulu {
+        return run_fulu(&header, &pre_state_ssz_bytes, inputs);
+    }
+    let mut state: BeaconState = deserialize(&pre_state_ssz_bytes, InputStage::DeserializePreState)?;
+
+    // Incremental merkleization compares the post-state against an untouched copy of the pre-state
+    let incremental = match header.merkleization {
+        Merkleization::Full => None,
+        Merkleization::Incremental => {
+            let cache =
+                StateRootCache::decode(&cached_roots).ok_or(InputStage::ValidateFieldRoots)?;
+            Some((state.clone(), cache))
+        }
+    };
+    if header.stop_after == Phase::Deserialize {
+        return Ok(GuestOutput::Stopped(Phase::Deserialize));
+    }
+
+    // The rewards deltas are committed instead of a post-state
+    if let [OperationInput::RewardsDeltas] = inputs.as_slice() {
+        return rewards_deltas(&state);
+    }
+    // State queries commit the requested validator fields with the unchanged state's root
+    if let [OperationInput::StateQuery { field, indices }] = inputs.as_slice() {
+        return state_query(&state, *field, indices);
+    }
 
     // Main logic of the program.
-    // State transition of the beacon state.
+    // State transition of the beacon state, applying a batch's operations in order.
 
     // eprintln!("{}:{}: {}", "process-operation", "start", env::cycle_count());
 
+    // Per-input markers split the cycles of a batch by input when uncommented
+    for (_index, input) in inputs.into_iter().enumerate() {
+        // eprintln!("{}:{}:{}: {}", "process-input", _index, "start", env::cycle_count());
+        apply(&mut state, input)?;
+        // eprintln!("{}:{}:{}: {}", "process-input", _index, "end", env::cycle_count());
+    }
+
+    // eprintln!("{}:{}: {}", "process-operation", "end", env::cycle_count());
+    if header.stop_after == Phase::Process {
+        return Ok(GuestOutput::Stopped(Phase::Process));
+    }
+
+    // Merkleize the processed state
+    // eprintln!("{}:{}: {}", "merkleize-operation", "start", env::cycle_count());
+    let output = match incremental {
+        None => GuestOutput::StateRoot(state.tree_hash_root()),
+        Some((pre_state, cache)) => GuestOutput::StateTransition {
+            supplied_pre_state_root: cache.root(),
+            state_root: cache
+                .incremental_state_root(&pre_state, &state)
+                .ok_or(InputStage::ValidateFieldRoots)?,
+        },
+    };
+    // eprintln!("{}:{}: {}", "merkleize-operation", "end", env::cycle_count());
+
+    Ok(output)
+}
+
+/// Applies a single operation to `state`. Errors returned by the state transition are ignored,
+/// since invalid operations are part of consensus-spec-tests.
+fn apply(state: &mut BeaconState, input: OperationInput) -> Result<(), InputStage> {
     match input {
         OperationInput::Attestation(ssz_bytes) => {
-            let attestation: Attestation = deserialize(&ssz_bytes);
+            let attestation: Attestation = deserialize(&ssz_bytes, InputStage::DeserializeOperation)?;
             let _ = state.process_attestation(&attestation);
         }
         OperationInput::AttesterSlashing(ssz_bytes) => {
-            let attester_slashing: AttesterSlashing = deserialize(&ssz_bytes);
+            let attester_slashing: AttesterSlashing = deserialize(&ssz_bytes, InputStage::DeserializeOperation)?;
             let _ = state.process_attester_slashing(&attester_slashing);
         }
         OperationInput::BeaconBlock(ssz_bytes) => {
-            let block: BeaconBlock = deserialize(&ssz_bytes);
+            let block: BeaconBlock = deserialize(&ssz_bytes, InputStage::DeserializeOperation)?;
             let _ = state.process_block_header(&block);
         }
         OperationInput::SignedBLSToExecutionChange(ssz_bytes) => {
-            let bls_change: SignedBLSToExecutionChange = deserialize(&ssz_bytes);
+            let bls_change: SignedBLSToExecutionChange = deserialize(&ssz_bytes, InputStage::DeserializeOperation)?;
             let _ = state.process_bls_to_execution_change(&bls_change);
         }
         OperationInput::Deposit(ssz_bytes) => {
-            let deposit: Deposit = deserialize(&ssz_bytes);
+            let deposit: Deposit = deserialize(&ssz_bytes, InputStage::DeserializeOperation)?;
             let _ = state.process_deposit(&deposit);
         }
         OperationInput::BeaconBlockBody(_ssz_bytes) => {
-            panic!("Not implemented");
-            // let block_body: BeaconBlockBody = deserialize(&ssz_bytes);
+            // `process_execution_payload` needs an execution engine, which the guest does not have
+            return Err(InputStage::UnsupportedOperation);
+            // let block_body: BeaconBlockBody = deserialize(&ssz_bytes, InputStage::DeserializeOperation)?;
             // let _ = state.process_execution_payload(&block_body);
         }
         OperationInput::ProposerSlashing(ssz_bytes) => {
-            let proposer_slashing: ProposerSlashing = deserialize(&ssz_bytes);
+            let proposer_slashing: ProposerSlashing = deserialize(&ssz_bytes, InputStage::DeserializeOperation)?;
             let _ = state.process_proposer_slashing(&proposer_slashing);
         }
         OperationInput::SyncAggregate(ssz_bytes) => {
-            let sync_aggregate: SyncAggregate = deserialize(&ssz_bytes);
+            let sync_aggregate: SyncAggregate = deserialize(&ssz_bytes, InputStage::DeserializeOperation)?;
             let _ = state.process_sync_aggregate(&sync_aggregate);
         }
         OperationInput::SignedVoluntaryExit(ssz_bytes) => {
-            let voluntary_exit: SignedVoluntaryExit = deserialize(&ssz_bytes);
+            let voluntary_exit: SignedVoluntaryExit = deserialize(&ssz_bytes, InputStage::DeserializeOperation)?;
             let _ = state.process_voluntary_exit(&voluntary_exit);
         }
         OperationInput::ExecutionPayload(ssz_bytes) => {
-            let execution_payload: ExecutionPayload = deserialize(&ssz_bytes);
+            let execution_payload: ExecutionPayload = deserialize(&ssz_bytes, InputStage::DeserializeOperation)?;
             let _ = state.process_withdrawals(&execution_payload);
         }
         OperationInput::EpochProcessing(epoch_type) => {
@@ -134,21 +284,490 @@ fn main() {
                 EpochProcessingType::SyncCommitteeUpdates => {
                     let _ = state.process_sync_committee_updates();
                 }
+                EpochProcessingType::Full => {
+                    let _ = state.process_epoch();
+                }
+                EpochProcessingType::ProposerLookahead => {
+                    // An Electra state has no proposer lookahead, see `run_fulu`
+                    return Err(InputStage::UnsupportedOperation);
+                }
             }
         }
         OperationInput::ProcessSlot => {
             let _ = state.process_slot();
         }
+        OperationInput::Noop => {}
+        OperationInput::SignedBeaconBlock(ssz_bytes) => {
+            let signed_block: SignedBeaconBlock = deserialize(&ssz_bytes, InputStage::DeserializeOperation)?;
+            let _ = state.state_transition(&signed_block, true);
+        }
+        // Only run on their own, see `rewards_deltas`, `ssz_static_root`, `shuffle`,
+        // `verify_merkle_proof` and `state_query`
+        OperationInput::RewardsDeltas
+        | OperationInput::SszStatic(..)
+        | OperationInput::Shuffle { .. }
+        | OperationInput::MerkleProof { .. }
+        | OperationInput::StateQuery { .. } => {
+            return Err(InputStage::UnsupportedOperation)
+        }
+        // Only run after a `ForkChoiceAnchor`, see `run_fork_choice`
+        OperationInput::ForkChoiceAnchor(_)
+        | OperationInput::OnTick(_)
+        | OperationInput::OnBlock { .. }
+        | OperationInput::OnAttestation { .. }
+        | OperationInput::OnAttesterSlashing { .. }
+        | OperationInput::ForkChoiceChecks => {
+            return Err(InputStage::UnsupportedOperation)
+        }
+        // Only run against a light client store, see `run_light_client_sync` and
+        // `rank_light_client_updates`
+        OperationInput::LightClientBootstrap { .. }
+        | OperationInput::LightClientUpdate { .. }
+        | OperationInput::LightClientForceUpdate(_)
+        | OperationInput::RankedLightClientUpdate(_) => {
+            return Err(InputStage::UnsupportedOperation)
+        }
+        // Handled by `run_upgrade` before the pre-state is decoded as an Electra state
+        OperationInput::UpgradeToFork
+        | OperationInput::PreForkSignedBeaconBlock(_)
+        | OperationInput::UpgradeAtEpoch(_) => return Err(InputStage::UnsupportedOperation),
     }
 
+    Ok(())
+}
+
+/// Decodes a standalone container and commits its hash tree root.
+fn ssz_static_root(ssz_type: SszType, ssz_bytes: &[u8]) -> Result<GuestOutput, InputStage> {
+    Ok(GuestOutput::SszRoot(container_root(ssz_type, ssz_bytes)?))
+}
+
+fn container_root(ssz_type: SszType, ssz_bytes: &[u8]) -> Result<Hash256, InputStage> {
+    let root = match ssz_type {
+        SszType::Attestation => hash_tree_root::<Attestation>(ssz_bytes)?,
+        SszType::AttesterSlashing => hash_tree_root::<AttesterSlashing>(ssz_bytes)?,
+        SszType::BeaconBlock => hash_tree_root::<BeaconBlock>(ssz_bytes)?,
+        SszType::BeaconBlockHeader => hash_tree_root::<BeaconBlockHeader>(ssz_bytes)?,
+        SszType::BeaconState => hash_tree_root::<BeaconState>(ssz_bytes)?,
+        SszType::Deposit => hash_tree_root::<Deposit>(ssz_bytes)?,
+        SszType::ExecutionPayload => hash_tree_root::<ExecutionPayload>(ssz_bytes)?,
+        SszType::ProposerSlashing => hash_tree_root::<ProposerSlashing>(ssz_bytes)?,
+        SszType::SignedBeaconBlock => hash_tree_root::<SignedBeaconBlock>(ssz_bytes)?,
+        SszType::SignedBLSToExecutionChange => {
+            hash_tree_root::<SignedBLSToExecutionChange>(ssz_bytes)?
+        }
+        SszType::SignedVoluntaryExit => hash_tree_root::<SignedVoluntaryExit>(ssz_bytes)?,
+        SszType::SyncAggregate => hash_tree_root::<SyncAggregate>(ssz_bytes)?,
+        SszType::AttestationData => hash_tree_root::<AttestationData>(ssz_bytes)?,
+        SszType::Checkpoint => hash_tree_root::<Checkpoint>(ssz_bytes)?,
+        SszType::DepositData => hash_tree_root::<DepositData>(ssz_bytes)?,
+        SszType::Eth1Data => hash_tree_root::<Eth1Data>(ssz_bytes)?,
+        SszType::Fork => hash_tree_root::<ream_consensus::fork::Fork>(ssz_bytes)?,
+        SszType::SyncCommittee => hash_tree_root::<SyncCommittee>(ssz_bytes)?,
+        SszType::Validator => hash_tree_root::<Validator>(ssz_bytes)?,
+        SszType::BeaconBlockBody => hash_tree_root::<BeaconBlockBody>(ssz_bytes)?,
+    };
+
+    Ok(root)
+}
+
+/// Hashes the object and verifies the branch of `leaf` at generalized index `gindex` against its
+/// root, then commits the root, leaf and generalized index.
+fn verify_merkle_proof(
+    ssz_type: SszType,
+    ssz_bytes: &[u8],
+    leaf: Hash256,
+    gindex: u64,
+    branch: &[Hash256],
+) -> Result<GuestOutput, InputStage> {
+    let root = container_root(ssz_type, ssz_bytes)?;
+
+    // eprintln!("{}:{}: {}", "process-operation", "start", env::cycle_count());
+    let valid = is_valid_gindex_branch(leaf, branch, gindex, root);
+    // eprintln!("{}:{}: {}", "process-operation", "end", env::cycle_count());
+    if !valid {
+        return Err(InputStage::ValidateMerkleProof);
+    }
+
+    Ok(GuestOutput::MerkleProof { root, leaf, gindex })
+}
+
+fn hash_tree_root<T: ssz::Decode + TreeHash>(ssz_bytes: &[u8]) -> Result<Hash256, InputStage> {
+    let value: T = deserialize(ssz_bytes, InputStage::DeserializeOperation)?;
+
+    // eprintln!("{}:{}: {}", "merkleize-operation", "start", env::cycle_count());
+    let root = value.tree_hash_root();
+    // eprintln!("{}:{}: {}", "merkleize-operation", "end", env::cycle_count());
+
+    Ok(root)
+}
+
+/// Computes the reward and penalty deltas of `state` and commits their digest.
+fn rewards_deltas(state: &BeaconState) -> Result<GuestOutput, InputStage> {
+    // eprintln!("{}:{}: {}", "process-operation", "start", env::cycle_count());
+    let deltas = compute_deltas(state).map_err(|_| InputStage::UnsupportedOperation)?;
+    // eprintln!("{}:{}: {}", "process-operation", "end", env::cycle_count());
+
+    // eprintln!("{}:{}: {}", "merkleize-operation", "start", env::cycle_count());
+    let digest = computed_deltas_digest(&deltas);
+    // eprintln!("{}:{}: {}", "merkleize-operation", "end", env::cycle_count());
+
+    Ok(GuestOutput::RewardsDeltas(digest))
+}
+
+/// Reads `field` of the validators at `indices` and commits them with the state root.
+fn state_query(
+    state: &BeaconState,
+    field: ValidatorField,
+    indices: &[u64],
+) -> Result<GuestOutput, InputStage> {
+    // eprintln!("{}:{}: {}", "process-operation", "start", env::cycle_count());
+    let values = query_validators(state, field, indices).ok_or(InputStage::ValidateStateQuery)?;
     // eprintln!("{}:{}: {}", "process-operation", "end", env::cycle_count());
 
-    // Merkleize the processed state
     // eprintln!("{}:{}: {}", "merkleize-operation", "start", env::cycle_count());
     let state_root = state.tree_hash_root();
     // eprintln!("{}:{}: {}", "merkleize-operation", "end", env::cycle_count());
 
-    // eprintln!("{}:{}: {}", "commit", "start", env::cycle_count());
-    commit(&state_root);
-    // eprintln!("{}:{}: {}", "commit", "end", env::cycle_count());
+    Ok(GuestOutput::StateQuery(QueryAnswer {
+        state_root,
+        field,
+        indices: indices.to_vec(),
+        values,
+    }))
+}
+
+/// Shuffles every index of `0..count` and commits a digest of the mapping.
+fn shuffle(seed: Hash256, count: u64) -> Result<GuestOutput, InputStage> {
+    // eprintln!("{}:{}: {}", "process-operation", "start", env::cycle_count());
+    let mapping = compute_mapping(seed, count).map_err(|_| InputStage::UnsupportedOperation)?;
+    // eprintln!("{}:{}: {}", "process-operation", "end", env::cycle_count());
+
+    // eprintln!("{}:{}: {}", "merkleize-operation", "start", env::cycle_count());
+    let digest = mapping_digest(&mapping);
+    // eprintln!("{}:{}: {}", "merkleize-operation", "end", env::cycle_count());
+
+    Ok(GuestOutput::ShuffleMapping(digest))
+}
+
+/// Applies the operation to a multiproof of the pre-state instead of the full `BeaconState`.
+fn run_witness(
+    header: &InputHeader,
+    witness_ssz_bytes: &[u8],
+    payload: Vec<u8>,
+) -> Result<GuestOutput, InputStage> {
+    // Witnesses are not split into phases
+    if header.stop_after != Phase::Merkleize {
+        return Err(InputStage::ReadHeader);
+    }
+
+    let (supplied_pre_state_root, state_root) = match header.kind {
+        OperationKind::ProcessSlot => {
+            let witness: ProcessSlotWitness =
+                deserialize(witness_ssz_bytes, InputStage::DeserializePreState)?;
+            // eprintln!("{}:{}: {}", "process-operation", "start", env::cycle_count());
+            let roots = witness.process_slot();
+            // eprintln!("{}:{}: {}", "process-operation", "end", env::cycle_count());
+            roots
+        }
+        OperationKind::SignedBLSToExecutionChange => {
+            let witness: BlsToExecutionChangeWitness =
+                deserialize(witness_ssz_bytes, InputStage::DeserializePreState)?;
+            let inputs =
+                decode_inputs(header, payload).ok_or(InputStage::ReadOperationInput)?;
+            let [OperationInput::SignedBLSToExecutionChange(ssz_bytes)] = inputs.as_slice() else {
+                return Err(InputStage::ReadOperationInput);
+            };
+            let bls_change: SignedBLSToExecutionChange =
+                deserialize(ssz_bytes, InputStage::DeserializeOperation)?;
+            // eprintln!("{}:{}: {}", "process-operation", "start", env::cycle_count());
+            let roots = witness.process_bls_to_execution_change(&bls_change);
+            // eprintln!("{}:{}: {}", "process-operation", "end", env::cycle_count());
+            roots
+        }
+        _ => return Err(InputStage::ReadHeader),
+    }
+    .ok_or(InputStage::ValidateWitness)?;
+
+    Ok(GuestOutput::StateTransition {
+        supplied_pre_state_root,
+        state_root,
+    })
+}
+
+/// Applies the operation to a lazily decoded pre-state, decoding only the fields it reads.
+fn run_lazy(header: &InputHeader, pre_state_ssz_bytes: &[u8]) -> Result<GuestOutput, InputStage> {
+    // Only `process_slot` runs on the lazy view so far, and it is not split into phases
+    if header.kind != OperationKind::ProcessSlot || header.stop_after != Phase::Merkleize {
+        return Err(InputStage::ReadHeader);
+    }
+
+    // eprintln!("{}-{}:{}: {}", "deserialize", "LazyBeaconState", "start", env::cycle_count());
+    let lazy_state = LazyBeaconState::new(pre_state_ssz_bytes)
+        .map_err(|_| InputStage::DeserializePreState)?;
+    // eprintln!("{}-{}:{}: {}", "deserialize", "LazyBeaconState", "end", env::cycle_count());
+
+    // The witness is built from the pre-state's own bytes, so its pre-state root is trusted.
+    // Building it hashes the pre-state's fields.
+    // eprintln!("{}:{}: {}", "merkleize-operation", "start", env::cycle_count());
+    let witness = ProcessSlotWitness::from_lazy_state(&lazy_state)
+        .map_err(|_| InputStage::DeserializePreState)?;
+    // eprintln!("{}:{}: {}", "merkleize-operation", "end", env::cycle_count());
+
+    // eprintln!("{}:{}: {}", "process-operation", "start", env::cycle_count());
+    let (_pre_state_root, state_root) =
+        witness.process_slot().ok_or(InputStage::ValidateWitness)?;
+    // eprintln!("{}:{}: {}", "process-operation", "end", env::cycle_count());
+
+    Ok(GuestOutput::StateRoot(state_root))
+}
+
+/// Builds a fork choice store from the anchor state and block, applies the remaining steps in
+/// order and commits a snapshot of the store at every `ForkChoiceChecks`.
+fn run_fork_choice(
+    anchor_state_ssz_bytes: &[u8],
+    inputs: Vec<OperationInput>,
+) -> Result<GuestOutput, InputStage> {
+    let mut inputs = inputs.into_iter();
+    let Some(OperationInput::ForkChoiceAnchor(anchor_block_ssz_bytes)) = inputs.next() else {
+        return Err(InputStage::ReadOperationInput);
+    };
+    let anchor_state: BeaconState =
+        deserialize(anchor_state_ssz_bytes, InputStage::DeserializePreState)?;
+    let anchor_block: BeaconBlock =
+        deserialize(&anchor_block_ssz_bytes, InputStage::DeserializeOperation)?;
+
+    // eprintln!("{}:{}: {}", "process-operation", "start", env::cycle_count());
+    let mut store = Store::new(anchor_state, anchor_block).ok_or(InputStage::ApplyOperation)?;
+    let mut snapshots = Vec::new();
+    for (_index, input) in inputs.enumerate() {
+        // eprintln!("{}:{}:{}: {}", "process-input", _index, "start", env::cycle_count());
+        let (accepted, valid) = match input {
+            OperationInput::OnTick(time) => {
+                store.on_tick(time);
+                (true, true)
+            }
+            OperationInput::OnBlock { block, valid } => {
+                let block: SignedBeaconBlock =
+                    deserialize(&block, InputStage::DeserializeOperation)?;
+                (store.on_block_and_operations(&block).is_ok(), valid)
+            }
+            OperationInput::OnAttestation { attestation, valid } => {
+                let attestation: Attestation =
+                    deserialize(&attestation, InputStage::DeserializeOperation)?;
+                (store.on_attestation(&attestation, false).is_ok(), valid)
+            }
+            OperationInput::OnAttesterSlashing {
+                attester_slashing,
+                valid,
+            } => {
+                let attester_slashing: AttesterSlashing =
+                    deserialize(&attester_slashing, InputStage::DeserializeOperation)?;
+                (store.on_attester_slashing(&attester_slashing).is_ok(), valid)
+            }
+            OperationInput::ForkChoiceChecks => {
+                snapshots.push(store.snapshot().map_err(|_| InputStage::ApplyOperation)?);
+                (true, true)
+            }
+            _ => return Err(InputStage::ReadOperationInput),
+        };
+        if accepted != valid {
+            return Err(InputStage::ApplyOperation);
+        }
+        // eprintln!("{}:{}:{}: {}", "process-input", _index, "end", env::cycle_count());
+    }
+    // eprintln!("{}:{}: {}", "process-operation", "end", env::cycle_count());
+
+    Ok(GuestOutput::ForkChoice(snapshots))
+}
+
+/// Initializes a light client store from the bootstrap, processes the remaining updates in order
+/// and commits the store's headers after each of them.
+fn run_light_client_sync(
+    bootstrap_ssz_bytes: &[u8],
+    inputs: Vec<OperationInput>,
+) -> Result<GuestOutput, InputStage> {
+    let mut inputs = inputs.into_iter();
+    let Some(OperationInput::LightClientBootstrap {
+        trusted_block_root,
+        genesis_validators_root,
+        fork_schedule,
+    }) = inputs.next()
+    else {
+        return Err(InputStage::ReadOperationInput);
+    };
+    let bootstrap: LightClientBootstrap =
+        deserialize(bootstrap_ssz_bytes, InputStage::DeserializePreState)?;
+
+    // eprintln!("{}:{}: {}", "process-operation", "start", env::cycle_count());
+    let mut store = LightClientStore::initialize(
+        trusted_block_root,
+        bootstrap,
+        genesis_validators_root,
+        fork_schedule,
+    )
+    .ok_or(InputStage::ValidateLightClientUpdate)?;
+    let mut snapshots = Vec::new();
+    for (_index, input) in inputs.enumerate() {
+        // eprintln!("{}:{}:{}: {}", "process-input", _index, "start", env::cycle_count());
+        match input {
+            OperationInput::LightClientUpdate {
+                update,
+                current_slot,
+            } => {
+                let update: LightClientUpdate =
+                    deserialize(&update, InputStage::DeserializeOperation)?;
+                if !store.process_update(update, current_slot) {
+                    return Err(InputStage::ValidateLightClientUpdate);
+                }
+            }
+            OperationInput::LightClientForceUpdate(current_slot) => {
+                if !store.force_update(current_slot) {
+                    return Err(InputStage::ValidateLightClientUpdate);
+                }
+            }
+            _ => return Err(InputStage::ReadOperationInput),
+        }
+        snapshots.push(store.snapshot());
+        // eprintln!("{}:{}:{}: {}", "process-input", _index, "end", env::cycle_count());
+    }
+    // eprintln!("{}:{}: {}", "process-operation", "end", env::cycle_count());
+
+    Ok(GuestOutput::LightClientSync(snapshots))
+}
+
+/// Checks that no update of a ranking case is better than the one listed before it.
+fn rank_light_client_updates(inputs: Vec<OperationInput>) -> Result<GuestOutput, InputStage> {
+    let updates = inputs
+        .into_iter()
+        .map(|input| match input {
+            OperationInput::RankedLightClientUpdate(ssz_bytes) => {
+                deserialize::<LightClientUpdate>(&ssz_bytes, InputStage::DeserializeOperation)
+            }
+            _ => Err(InputStage::ReadOperationInput),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // eprintln!("{}:{}: {}", "process-operation", "start", env::cycle_count());
+    let sorted = updates
+        .windows(2)
+        .all(|pair| !is_better_update(&pair[1], &pair[0]));
+    // eprintln!("{}:{}: {}", "process-operation", "end", env::cycle_count());
+
+    Ok(GuestOutput::LightClientUpdateRanking(sorted))
+}
+
+/// Applies Fulu-only epoch steps to a Fulu pre-state, split by [`FuluBeaconState`] into ream's
+/// Electra `BeaconState` and the proposer lookahead. Only `process_proposer_lookahead` runs on it
+/// so far.
+fn run_fulu(
+    header: &InputHeader,
+    pre_state_ssz_bytes: &[u8],
+    inputs: Vec<OperationInput>,
+) -> Result<GuestOutput, InputStage> {
+    if header.merkleization != Merkleization::Full {
+        return Err(InputStage::ReadHeader);
+    }
+
+    let mut state: FuluBeaconState =
+        deserialize(pre_state_ssz_bytes, InputStage::DeserializePreState)?;
+    if header.stop_after == Phase::Deserialize {
+        return Ok(GuestOutput::Stopped(Phase::Deserialize));
+    }
+
+    // eprintln!("{}:{}: {}", "process-operation", "start", env::cycle_count());
+    for input in inputs {
+        match input {
+            OperationInput::EpochProcessing(EpochProcessingType::ProposerLookahead) => {
+                let _ = state.process_proposer_lookahead();
+            }
+            OperationInput::Noop => {}
+            _ => return Err(InputStage::UnsupportedOperation),
+        }
+    }
+    // eprintln!("{}:{}: {}", "process-operation", "end", env::cycle_count());
+    if header.stop_after == Phase::Process {
+        return Ok(GuestOutput::Stopped(Phase::Process));
+    }
+
+    // eprintln!("{}:{}: {}", "merkleize-operation", "start", env::cycle_count());
+    let state_root = state.tree_hash_root();
+    // eprintln!("{}:{}: {}", "merkleize-operation", "end", env::cycle_count());
+
+    Ok(GuestOutput::StateRoot(state_root))
+}
+
+/// Applies inputs that start from a pre-state of the fork preceding `header.fork`: a fork upgrade,
+/// or the pre-fork blocks, upgrade and post-fork blocks of a transition case. Only the upgrade
+/// from Electra to Fulu is supported, since ream only exposes the Electra `BeaconState`.
+fn run_upgrade(
+    header: &InputHeader,
+    pre_state_ssz_bytes: &[u8],
+    inputs: Vec<OperationInput>,
+) -> Result<GuestOutput, InputStage> {
+    if header.merkleization != Merkleization::Full {
+        return Err(InputStage::ReadHeader);
+    }
+    if header.fork != Fork::Fulu {
+        return Err(InputStage::UnsupportedOperation);
+    }
+
+    let mut pre_state: BeaconState =
+        deserialize(pre_state_ssz_bytes, InputStage::DeserializePreState)?;
+    if header.stop_after == Phase::Deserialize {
+        return Ok(GuestOutput::Stopped(Phase::Deserialize));
+    }
+
+    // eprintln!("{}:{}: {}", "process-operation", "start", env::cycle_count());
+    // Transition cases are all valid, so a block or slot that fails to apply rejects the case
+    let mut inputs = inputs.into_iter().peekable();
+    while let Some(OperationInput::PreForkSignedBeaconBlock(ssz_bytes)) =
+        inputs.next_if(|input| matches!(input, OperationInput::PreForkSignedBeaconBlock(_)))
+    {
+        let signed_block: SignedBeaconBlock =
+            deserialize(&ssz_bytes, InputStage::DeserializeOperation)?;
+        pre_state
+            .state_transition(&signed_block, true)
+            .map_err(|_| InputStage::ApplyOperation)?;
+    }
+    match inputs.next() {
+        Some(OperationInput::UpgradeToFork) => {}
+        // The upgrade happens once the previous fork has processed the slots up to the fork epoch
+        Some(OperationInput::UpgradeAtEpoch(fork_epoch)) => {
+            let fork_slot = fork_epoch * SLOTS_PER_EPOCH;
+            if pre_state.slot < fork_slot {
+                pre_state
+                    .process_slots(fork_slot)
+                    .map_err(|_| InputStage::ApplyOperation)?;
+            }
+        }
+        _ => return Err(InputStage::UnsupportedOperation),
+    }
+    let mut state = FuluBeaconState::upgrade_from_electra(pre_state)
+        .map_err(|_| InputStage::ApplyOperation)?;
+    for input in inputs {
+        let OperationInput::SignedBeaconBlock(ssz_bytes) = input else {
+            return Err(InputStage::UnsupportedOperation);
+        };
+        let signed_block: SignedBeaconBlock =
+            deserialize(&ssz_bytes, InputStage::DeserializeOperation)?;
+        state
+            .state_transition(&signed_block, true)
+            .map_err(|err| {
+                if err.is::<ProposerDivergence>() {
+                    InputStage::UnsupportedOperation
+                } else {
+                    InputStage::ApplyOperation
+                }
+            })?;
+    }
+    // eprintln!("{}:{}: {}", "process-operation", "end", env::cycle_count());
+    if header.stop_after == Phase::Process {
+        return Ok(GuestOutput::Stopped(Phase::Process));
+    }
+
+    // eprintln!("{}:{}: {}", "merkleize-operation", "start", env::cycle_count());
+    let state_root = state.tree_hash_root();
+    // eprintln!("{}:{}: {}", "merkleize-operation", "end", env::cycle_count());
+
+    Ok(GuestOutput::StateRoot(state_root))
 }
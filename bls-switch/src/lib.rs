@@ -0,0 +1,46 @@
+//! zkcrypto's `bls12_381` with a runtime switch for pairings.
+//!
+//! ream-bls verifies a signature by checking a pairing equation. With pairings off, [`pairing`]
+//! returns the identity of `Gt` and [`multi_miller_loop`] the result of an empty loop, so every
+//! such equation holds without being computed. The guest turns pairings off for inputs whose
+//! frame header carries `SignatureVerification::Ignored`; hashing messages to G2 and aggregating
+//! public keys still run, and so do the pairings of the `Bls12` engine traits, which ream-bls does
+//! not use.
+
+#![no_std]
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+pub use backend::*;
+
+static PAIRINGS: AtomicBool = AtomicBool::new(true);
+
+/// Turns pairing computations on or off. They are on until the first call.
+pub fn set_pairings(enabled: bool) {
+    PAIRINGS.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether pairings are computed.
+pub fn pairings_enabled() -> bool {
+    PAIRINGS.load(Ordering::Relaxed)
+}
+
+/// [`backend::pairing`], or the identity of `Gt` with pairings off.
+#[cfg(feature = "pairings")]
+pub fn pairing(p: &G1Affine, q: &G2Affine) -> Gt {
+    if pairings_enabled() {
+        backend::pairing(p, q)
+    } else {
+        Gt::identity()
+    }
+}
+
+/// [`backend::multi_miller_loop`], or the loop over no terms with pairings off.
+#[cfg(all(feature = "pairings", feature = "alloc"))]
+pub fn multi_miller_loop(terms: &[(&G1Affine, &G2Prepared)]) -> MillerLoopResult {
+    if pairings_enabled() {
+        backend::multi_miller_loop(terms)
+    } else {
+        backend::multi_miller_loop(&[])
+    }
+}
@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Errors raised while loading ELF binaries and consensus-spec-tests fixtures.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Could not read file {path:?}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Could not decode snappy {path:?}: {source}")]
+    Snappy {
+        path: PathBuf,
+        #[source]
+        source: snap::Error,
+    },
+
+    #[error("Could not parse YAML {path:?}: {source}")]
+    Yaml {
+        path: PathBuf,
+        #[source]
+        source: serde_yaml::Error,
+    },
+
+    #[error("{path:?} has no {field}")]
+    MissingMetaField { path: PathBuf, field: &'static str },
+
+    #[error("Batch {name:?} in {path:?} has no test cases")]
+    EmptyBatch { path: PathBuf, name: String },
+
+    #[error("Unknown ssz_static type {name:?} for {path:?}")]
+    UnknownSszType { path: PathBuf, name: String },
+
+    #[error("Fork digest {fork_digest} of {path:?} matches no mainnet fork version")]
+    UnknownForkDigest { path: PathBuf, fork_digest: String },
+
+    #[error("Fork version {fork_version:?} of {path:?} is not 4 hex bytes")]
+    InvalidForkVersion { path: PathBuf, fork_version: String },
+
+    #[error("{path:?} has no validator for a witness to open")]
+    EmptyValidatorRegistry { path: PathBuf },
+
+    #[error("Steps of {path:?} have keys the fork choice store does not check: {keys:?}")]
+    UnsupportedStepKeys { path: PathBuf, keys: Vec<String> },
+
+    #[error("Could not decode {type_name} from {path:?}: {error:?}")]
+    SszDecode {
+        type_name: &'static str,
+        path: PathBuf,
+        error: ssz::DecodeError,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -1,17 +1,46 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::error::{Error, Result};
 use crate::snappy::decode_snappy;
+use crate::ssz::from_ssz_bytes;
 
-pub fn ssz_from_file(path: &Path) -> Vec<u8> {
-    let raw_bytes =
-        std::fs::read(path).unwrap_or_else(|e| panic!("Could not read file: {:?}: {}", path, e));
+pub fn read_file(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Decompresses snappy bytes that were loaded from `path`, attributing failures to that file.
+pub fn decode_snappy_bytes(raw_bytes: &[u8], path: &Path) -> Result<Vec<u8>> {
+    decode_snappy(raw_bytes).map_err(|source| Error::Snappy {
+        path: path.to_path_buf(),
+        source,
+    })
+}
 
-    decode_snappy(&raw_bytes).unwrap_or_else(|e| {
-        panic!("Could not decode snappy {:?}: {}", path, e);
+pub fn ssz_from_file(path: &Path) -> Result<Vec<u8>> {
+    let raw_bytes = read_file(path)?;
+
+    decode_snappy_bytes(&raw_bytes, path)
+}
+
+/// Decodes SSZ bytes that were loaded from `path`, attributing failures to that file.
+pub fn decode_ssz_bytes<T: ssz::Decode>(ssz_bytes: &[u8], path: &Path) -> Result<T> {
+    from_ssz_bytes(ssz_bytes).map_err(|error| Error::SszDecode {
+        type_name: std::any::type_name::<T>(),
+        path: path.to_path_buf(),
+        error,
     })
 }
 
+/// Reads a `.ssz_snappy` file and decodes it as `T`.
+pub fn decode_ssz_file<T: ssz::Decode>(path: &Path) -> Result<T> {
+    let ssz_bytes = ssz_from_file(path)?;
+    decode_ssz_bytes(&ssz_bytes, path)
+}
+
 pub fn get_test_cases(base_dir: &PathBuf) -> Vec<String> {
     let mut test_cases = Vec::new();
 
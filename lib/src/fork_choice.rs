@@ -0,0 +1,866 @@
+//! Steps and checks of the `fork_choice` spec tests.
+//!
+//! A case starts from `anchor_state` and `anchor_block` and applies the `steps.yaml` sequence of
+//! ticks, blocks, attestations and attester slashings to a fork choice store. The guest commits a
+//! [`StoreSnapshot`] at every `checks` step, which the host compares with the expected values.
+//!
+//! ream's fork choice store is backed by its database and drives the execution engine, so
+//! [`Store`] implements the Electra fork choice in memory instead, on top of ream's state
+//! transition. Execution payloads are not sent to an engine and blob data is assumed available;
+//! block steps that carry blobs are reported by [`Step::unsupported_keys`] instead.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use alloy_primitives::B256;
+use anyhow::{bail, ensure};
+use ream_consensus::{
+    attestation::Attestation,
+    attestation_data::AttestationData,
+    attester_slashing::AttesterSlashing,
+    checkpoint::Checkpoint,
+    electra::{
+        beacon_block::{BeaconBlock, SignedBeaconBlock},
+        beacon_state::BeaconState,
+    },
+    validator::Validator,
+};
+use serde::{Deserialize, Serialize, de::IgnoredAny};
+use tree_hash::TreeHash;
+
+use crate::error::{Error, Result};
+use crate::file::read_file;
+use crate::spec::SLOTS_PER_EPOCH;
+
+pub const SECONDS_PER_SLOT: u64 = 12;
+pub const INTERVALS_PER_SLOT: u64 = 3;
+pub const PROPOSER_SCORE_BOOST: u64 = 40;
+pub const GENESIS_EPOCH: u64 = 0;
+pub const EFFECTIVE_BALANCE_INCREMENT: u64 = 1_000_000_000;
+
+/// One entry of a case's `steps.yaml`. Keys of block and `checks` steps that are not checked,
+/// such as the blobs of a block, are kept so that [`Step::unsupported_keys`] can report them.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Step {
+    /// `on_tick` with the store time in seconds.
+    Tick { tick: u64 },
+    /// `on_block` with the `<block>.ssz_snappy` file of the case.
+    Block {
+        block: String,
+        #[serde(default = "valid_by_default")]
+        valid: bool,
+        #[serde(flatten)]
+        unsupported: BTreeMap<String, IgnoredAny>,
+    },
+    /// `on_attestation` with the `<attestation>.ssz_snappy` file of the case.
+    Attestation {
+        attestation: String,
+        #[serde(default = "valid_by_default")]
+        valid: bool,
+    },
+    /// `on_attester_slashing` with the `<attester_slashing>.ssz_snappy` file of the case.
+    AttesterSlashing {
+        attester_slashing: String,
+        #[serde(default = "valid_by_default")]
+        valid: bool,
+    },
+    /// Expected values of the store at this point.
+    Checks { checks: Checks },
+}
+
+fn valid_by_default() -> bool {
+    true
+}
+
+impl Step {
+    /// Keys of the step that the store does not apply or check, such as `blobs` and `proofs` of
+    /// a block or `get_proposer_head` of a `checks` step. A case with such keys is not validated.
+    pub fn unsupported_keys(&self) -> impl Iterator<Item = &str> {
+        let unsupported = match self {
+            Step::Block { unsupported, .. } => Some(unsupported),
+            Step::Checks { checks } => Some(&checks.unsupported),
+            _ => None,
+        };
+        unsupported
+            .into_iter()
+            .flat_map(|keys| keys.keys().map(String::as_str))
+    }
+}
+
+/// A checkpoint as written in `steps.yaml` and committed by the guest.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CheckpointValues {
+    pub epoch: u64,
+    pub root: B256,
+}
+
+/// Expected head of a `checks` step.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeadCheck {
+    pub slot: u64,
+    pub root: B256,
+}
+
+/// Expected values of a `checks` step. Only the present fields are compared; the other keys are
+/// kept in `unsupported`.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Checks {
+    pub time: Option<u64>,
+    pub head: Option<HeadCheck>,
+    pub justified_checkpoint: Option<CheckpointValues>,
+    pub finalized_checkpoint: Option<CheckpointValues>,
+    pub proposer_boost_root: Option<B256>,
+    #[serde(flatten)]
+    pub unsupported: BTreeMap<String, IgnoredAny>,
+}
+
+/// Store values committed by the guest at a `checks` step.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StoreSnapshot {
+    pub time: u64,
+    pub head_slot: u64,
+    pub head_root: B256,
+    pub justified_checkpoint: CheckpointValues,
+    pub finalized_checkpoint: CheckpointValues,
+    pub proposer_boost_root: B256,
+}
+
+impl Checks {
+    /// Whether every present field matches `snapshot`.
+    pub fn matches(&self, snapshot: &StoreSnapshot) -> bool {
+        self.time.is_none_or(|time| time == snapshot.time)
+            && self.head.is_none_or(|head| {
+                head.slot == snapshot.head_slot && head.root == snapshot.head_root
+            })
+            && self
+                .justified_checkpoint
+                .is_none_or(|checkpoint| checkpoint == snapshot.justified_checkpoint)
+            && self
+                .finalized_checkpoint
+                .is_none_or(|checkpoint| checkpoint == snapshot.finalized_checkpoint)
+            && self
+                .proposer_boost_root
+                .is_none_or(|root| root == snapshot.proposer_boost_root)
+    }
+}
+
+impl From<&Checkpoint> for CheckpointValues {
+    fn from(checkpoint: &Checkpoint) -> Self {
+        Self {
+            epoch: checkpoint.epoch,
+            root: checkpoint.root,
+        }
+    }
+}
+
+/// `LatestMessage` of a validator.
+#[derive(Debug, Clone, Copy)]
+struct LatestMessage {
+    epoch: u64,
+    root: B256,
+}
+
+/// What the store reads of a block once it is applied.
+#[derive(Debug, Clone, Copy)]
+struct BlockNode {
+    slot: u64,
+    parent_root: B256,
+    /// `current_justified_checkpoint` of the block's post-state.
+    justified_checkpoint: CheckpointValues,
+}
+
+/// Fork choice `Store`, kept in memory. Checkpoints are keyed by [`CheckpointValues`].
+///
+/// `checkpoint_states` only holds the states that needed empty slots processed up to their
+/// epoch; the other checkpoint states are the block states of their root.
+pub struct Store {
+    time: u64,
+    genesis_time: u64,
+    justified_checkpoint: CheckpointValues,
+    finalized_checkpoint: CheckpointValues,
+    unrealized_justified_checkpoint: CheckpointValues,
+    unrealized_finalized_checkpoint: CheckpointValues,
+    proposer_boost_root: B256,
+    equivocating_indices: BTreeSet<u64>,
+    blocks: BTreeMap<B256, BlockNode>,
+    block_states: BTreeMap<B256, BeaconState>,
+    checkpoint_states: BTreeMap<CheckpointValues, BeaconState>,
+    latest_messages: BTreeMap<u64, LatestMessage>,
+    unrealized_justifications: BTreeMap<B256, CheckpointValues>,
+}
+
+fn is_active_validator(validator: &Validator, epoch: u64) -> bool {
+    validator.activation_epoch <= epoch && epoch < validator.exit_epoch
+}
+
+fn is_slashable_attestation_data(data_1: &AttestationData, data_2: &AttestationData) -> bool {
+    // Double vote
+    (data_1 != data_2 && data_1.target.epoch == data_2.target.epoch)
+        // Surround vote
+        || (data_1.source.epoch < data_2.source.epoch && data_2.target.epoch < data_1.target.epoch)
+}
+
+impl Store {
+    /// `get_forkchoice_store`, or `None` if the anchor block does not commit to the anchor state.
+    pub fn new(anchor_state: BeaconState, anchor_block: BeaconBlock) -> Option<Self> {
+        if anchor_block.state_root != anchor_state.tree_hash_root() {
+            return None;
+        }
+        let anchor_root = anchor_block.tree_hash_root();
+        let anchor_checkpoint = CheckpointValues {
+            epoch: anchor_state.slot / SLOTS_PER_EPOCH,
+            root: anchor_root,
+        };
+        let anchor_node = BlockNode {
+            slot: anchor_block.slot,
+            parent_root: anchor_block.parent_root,
+            justified_checkpoint: (&anchor_state.current_justified_checkpoint).into(),
+        };
+
+        Some(Self {
+            time: anchor_state.genesis_time + SECONDS_PER_SLOT * anchor_state.slot,
+            genesis_time: anchor_state.genesis_time,
+            justified_checkpoint: anchor_checkpoint,
+            finalized_checkpoint: anchor_checkpoint,
+            unrealized_justified_checkpoint: anchor_checkpoint,
+            unrealized_finalized_checkpoint: anchor_checkpoint,
+            proposer_boost_root: B256::ZERO,
+            equivocating_indices: BTreeSet::new(),
+            blocks: BTreeMap::from([(anchor_root, anchor_node)]),
+            checkpoint_states: BTreeMap::new(),
+            block_states: BTreeMap::from([(anchor_root, anchor_state)]),
+            latest_messages: BTreeMap::new(),
+            unrealized_justifications: BTreeMap::from([(anchor_root, anchor_checkpoint)]),
+        })
+    }
+
+    fn current_slot(&self) -> u64 {
+        self.time.saturating_sub(self.genesis_time) / SECONDS_PER_SLOT
+    }
+
+    fn current_epoch(&self) -> u64 {
+        self.current_slot() / SLOTS_PER_EPOCH
+    }
+
+    /// `get_ancestor`: the block of the chain of `root` at or before `slot`.
+    fn get_ancestor(&self, root: B256, slot: u64) -> B256 {
+        let mut root = root;
+        while let Some(block) = self.blocks.get(&root) {
+            if block.slot <= slot {
+                break;
+            }
+            root = block.parent_root;
+        }
+        root
+    }
+
+    fn get_checkpoint_block(&self, root: B256, epoch: u64) -> B256 {
+        self.get_ancestor(root, epoch * SLOTS_PER_EPOCH)
+    }
+
+    /// State of a checkpoint stored by [`Store::store_target_checkpoint_state`].
+    fn checkpoint_state(&self, checkpoint: CheckpointValues) -> &BeaconState {
+        self.checkpoint_states
+            .get(&checkpoint)
+            .unwrap_or_else(|| &self.block_states[&checkpoint.root])
+    }
+
+    fn get_proposer_score(total_active_balance: u64) -> u64 {
+        let committee_weight =
+            total_active_balance.max(EFFECTIVE_BALANCE_INCREMENT) / SLOTS_PER_EPOCH;
+        committee_weight * PROPOSER_SCORE_BOOST / 100
+    }
+
+    /// Weight that the latest messages and the proposer boost give each block before the weights
+    /// of its descendants are added, in one pass over `latest_messages`.
+    fn get_votes(&self) -> BTreeMap<B256, u64> {
+        let state = self.checkpoint_state(self.justified_checkpoint);
+        let epoch = state.slot / SLOTS_PER_EPOCH;
+        let mut votes: BTreeMap<B256, u64> = BTreeMap::new();
+        for (index, message) in &self.latest_messages {
+            if self.equivocating_indices.contains(index) {
+                continue;
+            }
+            let Some(validator) = state.validators.get(*index as usize) else {
+                continue;
+            };
+            if is_active_validator(validator, epoch) && !validator.slashed {
+                *votes.entry(message.root).or_default() += validator.effective_balance;
+            }
+        }
+        if self.proposer_boost_root != B256::ZERO {
+            let total_active_balance = state
+                .validators
+                .iter()
+                .filter(|validator| is_active_validator(validator, epoch))
+                .map(|validator| validator.effective_balance)
+                .sum();
+            *votes.entry(self.proposer_boost_root).or_default() +=
+                Self::get_proposer_score(total_active_balance);
+        }
+        votes
+    }
+
+    /// `get_weight` of `root` and of every block under it: the votes of each block's subtree.
+    fn get_weights(
+        root: B256,
+        children: &BTreeMap<B256, Vec<B256>>,
+        votes: &BTreeMap<B256, u64>,
+        weights: &mut BTreeMap<B256, u64>,
+    ) -> u64 {
+        let mut weight = votes.get(&root).copied().unwrap_or(0);
+        for child in children.get(&root).into_iter().flatten() {
+            weight += Self::get_weights(*child, children, votes, weights);
+        }
+        weights.insert(root, weight);
+        weight
+    }
+
+    /// Children of every block, built once per [`Store::get_head`].
+    fn children(&self) -> BTreeMap<B256, Vec<B256>> {
+        let mut children: BTreeMap<B256, Vec<B256>> = BTreeMap::new();
+        for (root, block) in &self.blocks {
+            children.entry(block.parent_root).or_default().push(*root);
+        }
+        children
+    }
+
+    fn get_voting_source(&self, root: B256) -> CheckpointValues {
+        let block_epoch = self.blocks[&root].slot / SLOTS_PER_EPOCH;
+        if self.current_epoch() > block_epoch {
+            self.unrealized_justifications[&root]
+        } else {
+            self.blocks[&root].justified_checkpoint
+        }
+    }
+
+    fn is_previous_epoch_justified(&self) -> bool {
+        self.justified_checkpoint.epoch + 1 == self.current_epoch()
+    }
+
+    /// `filter_block_tree`: adds `root` to `viable` if a leaf under it has correct justified and
+    /// finalized checkpoints.
+    fn filter_block_tree(
+        &self,
+        root: B256,
+        children: &BTreeMap<B256, Vec<B256>>,
+        viable: &mut BTreeSet<B256>,
+    ) -> bool {
+        if let Some(root_children) = children.get(&root) {
+            // Every child is filtered, so that all viable leaves are recorded
+            let mut any_viable = false;
+            for child in root_children {
+                any_viable |= self.filter_block_tree(*child, children, viable);
+            }
+            if any_viable {
+                viable.insert(root);
+            }
+            return any_viable;
+        }
+
+        let current_epoch = self.current_epoch();
+        let voting_source = self.get_voting_source(root);
+        let mut correct_justified = self.justified_checkpoint.epoch == GENESIS_EPOCH
+            || voting_source.epoch == self.justified_checkpoint.epoch;
+        if !correct_justified && self.is_previous_epoch_justified() {
+            correct_justified = self.unrealized_justifications[&root].epoch
+                >= self.justified_checkpoint.epoch
+                && voting_source.epoch + 2 >= current_epoch;
+        }
+        let correct_finalized = self.finalized_checkpoint.epoch == GENESIS_EPOCH
+            || self.finalized_checkpoint.root
+                == self.get_checkpoint_block(root, self.finalized_checkpoint.epoch);
+        if correct_justified && correct_finalized {
+            viable.insert(root);
+        }
+        correct_justified && correct_finalized
+    }
+
+    /// `get_head`: walks down from the justified checkpoint to the heaviest viable child, breaking
+    /// ties by root.
+    pub fn get_head(&mut self) -> anyhow::Result<B256> {
+        let justified_root = self.justified_checkpoint.root;
+        ensure!(
+            self.blocks.contains_key(&justified_root),
+            "unknown justified block"
+        );
+        self.store_target_checkpoint_state(self.justified_checkpoint)?;
+
+        let children = self.children();
+        let mut viable = BTreeSet::new();
+        self.filter_block_tree(justified_root, &children, &mut viable);
+        let mut weights = BTreeMap::new();
+        Self::get_weights(justified_root, &children, &self.get_votes(), &mut weights);
+        let mut head = justified_root;
+        loop {
+            let heaviest_child = children
+                .get(&head)
+                .into_iter()
+                .flatten()
+                .filter(|child| viable.contains(*child))
+                .map(|child| (weights[child], *child))
+                .max();
+            match heaviest_child {
+                Some((_, child)) => head = child,
+                None => return Ok(head),
+            }
+        }
+    }
+
+    fn update_checkpoints(&mut self, justified: CheckpointValues, finalized: CheckpointValues) {
+        if justified.epoch > self.justified_checkpoint.epoch {
+            self.justified_checkpoint = justified;
+        }
+        if finalized.epoch > self.finalized_checkpoint.epoch {
+            self.finalized_checkpoint = finalized;
+        }
+    }
+
+    fn update_unrealized_checkpoints(
+        &mut self,
+        justified: CheckpointValues,
+        finalized: CheckpointValues,
+    ) {
+        if justified.epoch > self.unrealized_justified_checkpoint.epoch {
+            self.unrealized_justified_checkpoint = justified;
+        }
+        if finalized.epoch > self.unrealized_finalized_checkpoint.epoch {
+            self.unrealized_finalized_checkpoint = finalized;
+        }
+    }
+
+    fn on_tick_per_slot(&mut self, time: u64) {
+        let previous_slot = self.current_slot();
+        self.time = time;
+        let current_slot = self.current_slot();
+        if current_slot > previous_slot {
+            self.proposer_boost_root = B256::ZERO;
+            if current_slot % SLOTS_PER_EPOCH == 0 {
+                self.update_checkpoints(
+                    self.unrealized_justified_checkpoint,
+                    self.unrealized_finalized_checkpoint,
+                );
+            }
+        }
+    }
+
+    /// `on_tick`, running the per-slot updates of every slot up to `time`.
+    pub fn on_tick(&mut self, time: u64) {
+        let tick_slot = time.saturating_sub(self.genesis_time) / SECONDS_PER_SLOT;
+        while self.current_slot() < tick_slot {
+            let previous_time = self.genesis_time + (self.current_slot() + 1) * SECONDS_PER_SLOT;
+            self.on_tick_per_slot(previous_time);
+        }
+        self.on_tick_per_slot(time);
+    }
+
+    /// `on_block`, without the data availability check.
+    pub fn on_block(&mut self, signed_block: &SignedBeaconBlock) -> anyhow::Result<()> {
+        let block = &signed_block.message;
+        ensure!(
+            self.block_states.contains_key(&block.parent_root),
+            "unknown parent block"
+        );
+        ensure!(
+            self.current_slot() >= block.slot,
+            "block is from a future slot"
+        );
+        let finalized_slot = self.finalized_checkpoint.epoch * SLOTS_PER_EPOCH;
+        ensure!(
+            block.slot > finalized_slot,
+            "block is not after the finalized slot"
+        );
+        ensure!(
+            self.finalized_checkpoint.root
+                == self.get_checkpoint_block(block.parent_root, self.finalized_checkpoint.epoch),
+            "block does not descend from the finalized checkpoint"
+        );
+
+        // The parent state stays in the store for other children, so the block gets a copy
+        let mut state = self.block_states[&block.parent_root].clone();
+        state.state_transition(signed_block, true)?;
+        let justified = (&state.current_justified_checkpoint).into();
+        let finalized = (&state.finalized_checkpoint).into();
+        let block_root = block.tree_hash_root();
+        let node = BlockNode {
+            slot: block.slot,
+            parent_root: block.parent_root,
+            justified_checkpoint: justified,
+        };
+        self.blocks.insert(block_root, node);
+        self.block_states.insert(block_root, state);
+
+        // Add proposer score boost if the block is timely and the first one of its slot
+        let time_into_slot = (self.time - self.genesis_time) % SECONDS_PER_SLOT;
+        let is_before_attesting_interval = time_into_slot < SECONDS_PER_SLOT / INTERVALS_PER_SLOT;
+        let is_timely = self.current_slot() == block.slot && is_before_attesting_interval;
+        if is_timely && self.proposer_boost_root == B256::ZERO {
+            self.proposer_boost_root = block_root;
+        }
+
+        self.update_checkpoints(justified, finalized);
+        self.compute_pulled_up_tip(block_root)
+    }
+
+    /// `on_block`, followed by `on_attestation` and `on_attester_slashing` for the attestations
+    /// and attester slashings of its body, as a `block` step of the spec tests implies.
+    pub fn on_block_and_operations(
+        &mut self,
+        signed_block: &SignedBeaconBlock,
+    ) -> anyhow::Result<()> {
+        self.on_block(signed_block)?;
+        let body = &signed_block.message.body;
+        for attestation in body.attestations.iter() {
+            self.on_attestation(attestation, true)?;
+        }
+        for attester_slashing in body.attester_slashings.iter() {
+            self.on_attester_slashing(attester_slashing)?;
+        }
+
+        Ok(())
+    }
+
+    /// `compute_pulled_up_tip`. `process_justification_and_finalization` runs on the block's
+    /// state itself, whose checkpoint fields are restored afterwards instead of copying the state.
+    fn compute_pulled_up_tip(&mut self, block_root: B256) -> anyhow::Result<()> {
+        let Some(state) = self.block_states.get_mut(&block_root) else {
+            bail!("unknown block");
+        };
+        let saved_bits = state.justification_bits.clone();
+        let [saved_previous, saved_current, saved_finalized] = [
+            &state.previous_justified_checkpoint,
+            &state.current_justified_checkpoint,
+            &state.finalized_checkpoint,
+        ]
+        .map(|checkpoint| Checkpoint {
+            epoch: checkpoint.epoch,
+            root: checkpoint.root,
+        });
+        let processed = state.process_justification_and_finalization();
+        let justified: CheckpointValues = (&state.current_justified_checkpoint).into();
+        let finalized: CheckpointValues = (&state.finalized_checkpoint).into();
+        state.justification_bits = saved_bits;
+        state.previous_justified_checkpoint = saved_previous;
+        state.current_justified_checkpoint = saved_current;
+        state.finalized_checkpoint = saved_finalized;
+        processed?;
+
+        self.unrealized_justifications.insert(block_root, justified);
+        self.update_unrealized_checkpoints(justified, finalized);
+        // A block of a past epoch has its unrealized checkpoints realized right away
+        let block_epoch = self.blocks[&block_root].slot / SLOTS_PER_EPOCH;
+        if block_epoch < self.current_epoch() {
+            self.update_checkpoints(justified, finalized);
+        }
+
+        Ok(())
+    }
+
+    /// `store_target_checkpoint_state`. A block state already at or past the epoch start is the
+    /// checkpoint state as is, so only states that need empty slots processed are copied.
+    fn store_target_checkpoint_state(&mut self, target: CheckpointValues) -> anyhow::Result<()> {
+        if self.checkpoint_states.contains_key(&target) {
+            return Ok(());
+        }
+        let Some(base_state) = self.block_states.get(&target.root) else {
+            bail!("unknown checkpoint block");
+        };
+
+        let epoch_start_slot = target.epoch * SLOTS_PER_EPOCH;
+        if base_state.slot < epoch_start_slot {
+            let mut base_state = base_state.clone();
+            base_state.process_slots(epoch_start_slot)?;
+            self.checkpoint_states.insert(target, base_state);
+        }
+
+        Ok(())
+    }
+
+    /// `on_attestation`, checking the target epoch against the store time unless the attestation
+    /// comes from a block.
+    pub fn on_attestation(
+        &mut self,
+        attestation: &Attestation,
+        is_from_block: bool,
+    ) -> anyhow::Result<()> {
+        let data = &attestation.data;
+        let target: CheckpointValues = (&data.target).into();
+        if !is_from_block {
+            let current_epoch = self.current_epoch();
+            let previous_epoch = current_epoch.saturating_sub(1);
+            ensure!(
+                target.epoch == current_epoch || target.epoch == previous_epoch,
+                "attestation target is neither the current nor the previous epoch"
+            );
+        }
+        ensure!(
+            target.epoch == data.slot / SLOTS_PER_EPOCH,
+            "target epoch does not match the slot"
+        );
+        ensure!(
+            self.blocks.contains_key(&target.root),
+            "unknown target block"
+        );
+        let Some(block) = self.blocks.get(&data.beacon_block_root) else {
+            bail!("unknown attested block");
+        };
+        ensure!(
+            block.slot <= data.slot,
+            "attested block is after the attestation slot"
+        );
+        ensure!(
+            target.root == self.get_checkpoint_block(data.beacon_block_root, target.epoch),
+            "target is not an ancestor of the attested block"
+        );
+        ensure!(
+            self.current_slot() > data.slot,
+            "attestation is not from a past slot"
+        );
+
+        self.store_target_checkpoint_state(target)?;
+        let target_state = self.checkpoint_state(target);
+        let indexed_attestation = target_state.get_indexed_attestation(attestation)?;
+        ensure!(
+            target_state.is_valid_indexed_attestation(&indexed_attestation)?,
+            "invalid indexed attestation"
+        );
+
+        for index in indexed_attestation.attesting_indices.iter().copied() {
+            if self.equivocating_indices.contains(&index) {
+                continue;
+            }
+            let is_newer = self
+                .latest_messages
+                .get(&index)
+                .is_none_or(|message| target.epoch > message.epoch);
+            if is_newer {
+                let message = LatestMessage {
+                    epoch: target.epoch,
+                    root: data.beacon_block_root,
+                };
+                self.latest_messages.insert(index, message);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `on_attester_slashing`: the validators both attestations slash no longer count in the
+    /// weights.
+    pub fn on_attester_slashing(
+        &mut self,
+        attester_slashing: &AttesterSlashing,
+    ) -> anyhow::Result<()> {
+        let attestation_1 = &attester_slashing.attestation_1;
+        let attestation_2 = &attester_slashing.attestation_2;
+        ensure!(
+            is_slashable_attestation_data(&attestation_1.data, &attestation_2.data),
+            "attestations are not slashable"
+        );
+        let Some(state) = self.block_states.get(&self.justified_checkpoint.root) else {
+            bail!("unknown justified block");
+        };
+        ensure!(
+            state.is_valid_indexed_attestation(attestation_1)?
+                && state.is_valid_indexed_attestation(attestation_2)?,
+            "invalid indexed attestation"
+        );
+
+        let indices_2: BTreeSet<u64> = attestation_2.attesting_indices.iter().copied().collect();
+        let slashed = attestation_1
+            .attesting_indices
+            .iter()
+            .filter(|index| indices_2.contains(index));
+        self.equivocating_indices.extend(slashed);
+
+        Ok(())
+    }
+
+    /// Store values compared by the `checks` steps, with the head from [`Store::get_head`].
+    pub fn snapshot(&mut self) -> anyhow::Result<StoreSnapshot> {
+        let head_root = self.get_head()?;
+
+        Ok(StoreSnapshot {
+            time: self.time,
+            head_slot: self.blocks[&head_root].slot,
+            head_root,
+            justified_checkpoint: self.justified_checkpoint,
+            finalized_checkpoint: self.finalized_checkpoint,
+            proposer_boost_root: self.proposer_boost_root,
+        })
+    }
+}
+
+/// Loads `steps.yaml` from `case_dir`.
+pub fn load_steps(case_dir: &Path) -> Result<Vec<Step>> {
+    let path = case_dir.join("steps.yaml");
+    let bytes = read_file(&path)?;
+
+    serde_yaml::from_slice(&bytes).map_err(|source| Error::Yaml { path, source })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(byte: u8) -> B256 {
+        B256::repeat_byte(byte)
+    }
+
+    fn checkpoint(epoch: u64, root: B256) -> CheckpointValues {
+        CheckpointValues { epoch, root }
+    }
+
+    /// A store anchored at `root(1)` in slot 0, whose state has `count` active validators.
+    fn store(count: u64) -> Store {
+        let mut state = BeaconState::default();
+        for _ in 0..count {
+            let validator = Validator {
+                effective_balance: 32 * EFFECTIVE_BALANCE_INCREMENT,
+                activation_epoch: 0,
+                exit_epoch: u64::MAX,
+                ..Validator::default()
+            };
+            state.validators.push(validator).unwrap();
+        }
+        let anchor = checkpoint(0, root(1));
+        let anchor_node = BlockNode {
+            slot: 0,
+            parent_root: B256::ZERO,
+            justified_checkpoint: anchor,
+        };
+
+        Store {
+            time: 0,
+            genesis_time: 0,
+            justified_checkpoint: anchor,
+            finalized_checkpoint: anchor,
+            unrealized_justified_checkpoint: anchor,
+            unrealized_finalized_checkpoint: anchor,
+            proposer_boost_root: B256::ZERO,
+            equivocating_indices: BTreeSet::new(),
+            blocks: BTreeMap::from([(root(1), anchor_node)]),
+            block_states: BTreeMap::from([(root(1), state)]),
+            checkpoint_states: BTreeMap::new(),
+            latest_messages: BTreeMap::new(),
+            unrealized_justifications: BTreeMap::from([(root(1), anchor)]),
+        }
+    }
+
+    /// Adds `block_root` at `slot` under `parent_root`, with `justified` as the justified
+    /// checkpoint of its state.
+    fn add_block(
+        store: &mut Store,
+        block_root: B256,
+        parent_root: B256,
+        slot: u64,
+        justified: CheckpointValues,
+    ) {
+        let node = BlockNode {
+            slot,
+            parent_root,
+            justified_checkpoint: justified,
+        };
+        store.blocks.insert(block_root, node);
+        store
+            .unrealized_justifications
+            .insert(block_root, justified);
+    }
+
+    fn vote(store: &mut Store, index: u64, block_root: B256) {
+        let message = LatestMessage {
+            epoch: 0,
+            root: block_root,
+        };
+        store.latest_messages.insert(index, message);
+    }
+
+    #[test]
+    fn get_head_follows_weight_and_breaks_ties_by_root() {
+        let mut store = store(4);
+        let anchor = checkpoint(0, root(1));
+        add_block(&mut store, root(2), root(1), 1, anchor);
+        add_block(&mut store, root(3), root(1), 1, anchor);
+        store.time = 2 * SECONDS_PER_SLOT;
+        assert_eq!(store.get_head().unwrap(), root(3));
+
+        vote(&mut store, 0, root(2));
+        assert_eq!(store.get_head().unwrap(), root(2));
+
+        // An equivocating validator's vote does not count
+        vote(&mut store, 1, root(3));
+        vote(&mut store, 2, root(3));
+        store.equivocating_indices.insert(2);
+        assert_eq!(store.get_head().unwrap(), root(3));
+
+        store.proposer_boost_root = root(2);
+        assert_eq!(store.get_head().unwrap(), root(2));
+
+        // A vote for a descendant counts for every ancestor
+        store.proposer_boost_root = B256::ZERO;
+        add_block(&mut store, root(4), root(2), 2, anchor);
+        vote(&mut store, 3, root(4));
+        assert_eq!(store.get_head().unwrap(), root(4));
+    }
+
+    #[test]
+    fn filter_block_tree_keeps_branches_with_a_viable_leaf() {
+        let mut store = store(0);
+        let justified = checkpoint(1, root(1));
+        let stale = checkpoint(0, root(1));
+        store.justified_checkpoint = justified;
+        store.time = (SLOTS_PER_EPOCH + 8) * SECONDS_PER_SLOT;
+        add_block(&mut store, root(2), root(1), SLOTS_PER_EPOCH + 1, justified);
+        add_block(&mut store, root(3), root(1), SLOTS_PER_EPOCH + 2, stale);
+        add_block(&mut store, root(4), root(3), SLOTS_PER_EPOCH + 3, justified);
+        add_block(&mut store, root(5), root(2), SLOTS_PER_EPOCH + 4, stale);
+
+        let mut viable = BTreeSet::new();
+        assert!(store.filter_block_tree(root(1), &store.children(), &mut viable));
+        assert_eq!(viable, BTreeSet::from([root(1), root(3), root(4)]));
+    }
+
+    #[test]
+    fn on_tick_pulls_up_unrealized_checkpoints_at_epoch_start() {
+        let mut store = store(0);
+        let justified = checkpoint(2, root(3));
+        let finalized = checkpoint(1, root(2));
+        store.unrealized_justified_checkpoint = justified;
+        store.unrealized_finalized_checkpoint = finalized;
+        store.proposer_boost_root = root(2);
+
+        store.on_tick(5 * SECONDS_PER_SLOT);
+        assert_eq!(store.proposer_boost_root, B256::ZERO);
+        assert_eq!(store.justified_checkpoint, checkpoint(0, root(1)));
+        assert_eq!(store.finalized_checkpoint, checkpoint(0, root(1)));
+
+        let epoch_start = SLOTS_PER_EPOCH * SECONDS_PER_SLOT;
+        store.on_tick(epoch_start + 4);
+        assert_eq!(store.time, epoch_start + 4);
+        assert_eq!(store.justified_checkpoint, justified);
+        assert_eq!(store.finalized_checkpoint, finalized);
+
+        // Later ticks of the same slot keep the proposer boost
+        store.proposer_boost_root = root(4);
+        store.on_tick(epoch_start + 8);
+        assert_eq!(store.proposer_boost_root, root(4));
+    }
+
+    #[test]
+    fn steps_report_keys_the_store_does_not_check() {
+        let yaml = "
+- tick: 12
+- block: block_0x01
+  blobs: blobs_0x01
+  proofs: [0x02]
+- checks:
+    time: 12
+    get_proposer_head: '0x03'
+";
+        let steps: Vec<Step> = serde_yaml::from_str(yaml).unwrap();
+        let keys: Vec<&str> = steps.iter().flat_map(Step::unsupported_keys).collect();
+        assert_eq!(keys, ["blobs", "proofs", "get_proposer_head"]);
+        assert!(matches!(steps[1], Step::Block { valid: true, .. }));
+    }
+}
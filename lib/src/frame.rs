@@ -0,0 +1,477 @@
+//! Versioned framing of the guest's stdin.
+//!
+//! The host writes four raw stdin buffers. None of them is serde-encoded, so the guest can take
+//! ownership of each buffer as-is instead of allocating and copying it again:
+//!
+//! | # | Buffer    | Contents                                                                      |
+//! |---|-----------|-------------------------------------------------------------------------------|
+//! | 1 | header    | [`HEADER_LEN`] bytes, see below                                               |
+//! | 2 | pre-state | SSZ `BeaconState` or witness per [`StateInput`], compressed per [`Transport`] |
+//! | 3 | payload   | SSZ bytes of the operation, empty for operations without one                  |
+//! | 4 | roots     | Cached pre-state roots for [`Merkleization::Incremental`], else empty         |
+//!
+//! Header layout of [`FRAME_VERSION`] 1:
+//!
+//! | Offset | Field         | Encoding                                                              |
+//! |--------|---------------|-----------------------------------------------------------------------|
+//! | 0..4   | magic         | [`MAGIC`]                                                             |
+//! | 4      | version       | [`FRAME_VERSION`]                                                     |
+//! | 5      | fork          | [`Fork`] as `u8`                                                      |
+//! | 6      | preset        | [`Preset`] as `u8`                                                    |
+//! | 7      | kind          | [`OperationKind`] as `u8`                                             |
+//! | 8      | step          | [`EpochProcessingType`], [`SszType`] or step validity as `u8`, else 0 |
+//! | 9      | transport     | [`Transport`] of the pre-state buffer as `u8`                         |
+//! | 10     | signatures    | [`SignatureVerification`] as `u8`                                     |
+//! | 11     | merkleization | [`Merkleization`] as `u8`                                             |
+//! | 12     | state         | [`StateInput`] as `u8`                                                |
+//! | 13     | decoding      | [`Decoding`] as `u8`                                                  |
+//! | 14     | stop after    | [`Phase`] as `u8`                                                     |
+//! | 15     | -             | reserved, must be zero                                                |
+//!
+//! A header of kind [`OperationKind::Batch`] applies several operations to the same pre-state. Its
+//! payload buffer concatenates one entry per operation:
+//!
+//! | Offset   | Field   | Encoding                                   |
+//! |----------|---------|--------------------------------------------|
+//! | 0        | kind    | [`OperationKind`] as `u8`, never `Batch`   |
+//! | 1        | step    | as in the header                           |
+//! | 2..6     | length  | payload length as little-endian `u32`      |
+//! | 6..6+len | payload | SSZ bytes of the operation, possibly empty |
+//!
+//! The `step` of a fork choice step, such as [`OperationKind::OnBlock`], is 1 when the spec
+//! expects the store to accept it and 0 otherwise. The `step` of an [`OperationKind::StateQuery`]
+//! is the [`ValidatorField`] it reads, as `u8`.
+//!
+//! [`EpochProcessingType`]: crate::input::EpochProcessingType
+//! [`SszType`]: crate::ssz_static::SszType
+//! [`ValidatorField`]: crate::state_query::ValidatorField
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::{OperationInput, OperationKind};
+
+pub const MAGIC: [u8; 4] = *b"CPIN";
+pub const FRAME_VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Fork {
+    Electra,
+    Fulu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Preset {
+    Mainnet,
+    Minimal,
+}
+
+/// Encoding of the pre-state buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Transport {
+    /// Raw SSZ bytes, decompressed on the host.
+    Raw,
+    /// Snappy (raw block format) compressed SSZ bytes, decompressed in the guest.
+    Snappy,
+}
+
+/// Whether the guest should verify BLS signatures while applying the operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SignatureVerification {
+    Required,
+    Ignored,
+}
+
+/// How the guest merkleizes the post-state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Merkleization {
+    /// Hash the whole post-state.
+    Full,
+    /// Re-hash only changed fields and leaves on top of cached pre-state roots, see
+    /// [`crate::merkle`].
+    Incremental,
+}
+
+/// What the pre-state buffer carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum StateInput {
+    /// The full `BeaconState`.
+    Full,
+    /// An SSZ-encoded multiproof of the pre-state, see [`crate::witness`].
+    Witness,
+}
+
+/// How the guest decodes the pre-state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Decoding {
+    /// Decode the whole `BeaconState` up front.
+    Eager,
+    /// Decode fields on first access through [`crate::ssz::LazyBeaconState`].
+    Lazy,
+}
+
+/// Last phase the guest runs before committing, so the fixed cost of each phase can be measured
+/// on its own.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Phase {
+    /// Read (and decompress) every stdin buffer.
+    Read,
+    /// Decode the pre-state and the operation input. SSZ payloads of operations are decoded
+    /// right before they are applied, so they count towards [`Phase::Process`].
+    Deserialize,
+    /// Apply the operation.
+    Process,
+    /// Merkleize the post-state, i.e. run the whole program.
+    Merkleize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputHeader {
+    pub fork: Fork,
+    pub preset: Preset,
+    pub kind: OperationKind,
+    pub step: u8,
+    pub transport: Transport,
+    pub signatures: SignatureVerification,
+    pub merkleization: Merkleization,
+    pub state_input: StateInput,
+    pub decoding: Decoding,
+    pub stop_after: Phase,
+}
+
+impl Fork {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Fork::Electra),
+            1 => Some(Fork::Fulu),
+            _ => None,
+        }
+    }
+}
+
+impl Preset {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Preset::Mainnet),
+            1 => Some(Preset::Minimal),
+            _ => None,
+        }
+    }
+}
+
+impl Transport {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Transport::Raw),
+            1 => Some(Transport::Snappy),
+            _ => None,
+        }
+    }
+}
+
+impl SignatureVerification {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(SignatureVerification::Required),
+            1 => Some(SignatureVerification::Ignored),
+            _ => None,
+        }
+    }
+}
+
+impl Merkleization {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Merkleization::Full),
+            1 => Some(Merkleization::Incremental),
+            _ => None,
+        }
+    }
+}
+
+impl StateInput {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(StateInput::Full),
+            1 => Some(StateInput::Witness),
+            _ => None,
+        }
+    }
+}
+
+impl Decoding {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Decoding::Eager),
+            1 => Some(Decoding::Lazy),
+            _ => None,
+        }
+    }
+}
+
+impl Phase {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Phase::Read),
+            1 => Some(Phase::Deserialize),
+            2 => Some(Phase::Process),
+            3 => Some(Phase::Merkleize),
+            _ => None,
+        }
+    }
+}
+
+impl InputHeader {
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4] = FRAME_VERSION;
+        bytes[5] = self.fork as u8;
+        bytes[6] = self.preset as u8;
+        bytes[7] = self.kind as u8;
+        bytes[8] = self.step;
+        bytes[9] = self.transport as u8;
+        bytes[10] = self.signatures as u8;
+        bytes[11] = self.merkleization as u8;
+        bytes[12] = self.state_input as u8;
+        bytes[13] = self.decoding as u8;
+        bytes[14] = self.stop_after as u8;
+        bytes
+    }
+
+    /// Parses a header, rejecting unknown magic, versions, tags and non-zero reserved bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != HEADER_LEN || bytes[0..4] != MAGIC || bytes[4] != FRAME_VERSION {
+            return None;
+        }
+        if bytes[15..].iter().any(|byte| *byte != 0) {
+            return None;
+        }
+
+        Some(Self {
+            fork: Fork::from_u8(bytes[5])?,
+            preset: Preset::from_u8(bytes[6])?,
+            kind: OperationKind::from_u8(bytes[7])?,
+            step: bytes[8],
+            transport: Transport::from_u8(bytes[9])?,
+            signatures: SignatureVerification::from_u8(bytes[10])?,
+            merkleization: Merkleization::from_u8(bytes[11])?,
+            state_input: StateInput::from_u8(bytes[12])?,
+            decoding: Decoding::from_u8(bytes[13])?,
+            stop_after: Phase::from_u8(bytes[14])?,
+        })
+    }
+}
+
+/// Header fields chosen by the host for a whole run, independent of the operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameOptions {
+    pub fork: Fork,
+    pub preset: Preset,
+    pub transport: Transport,
+    pub signatures: SignatureVerification,
+    pub merkleization: Merkleization,
+    pub state_input: StateInput,
+    pub decoding: Decoding,
+    pub stop_after: Phase,
+}
+
+/// Splits `input` into the header and the payload buffer to write after the pre-state.
+pub fn encode_input(options: FrameOptions, input: OperationInput) -> (InputHeader, Vec<u8>) {
+    let (kind, step, payload) = input.into_parts();
+    let header = InputHeader {
+        fork: options.fork,
+        preset: options.preset,
+        kind,
+        step,
+        transport: options.transport,
+        signatures: options.signatures,
+        merkleization: options.merkleization,
+        state_input: options.state_input,
+        decoding: options.decoding,
+        stop_after: options.stop_after,
+    };
+
+    (header, payload)
+}
+
+/// Rebuilds the operation described by `header` around the payload buffer read from stdin.
+pub fn decode_input(header: &InputHeader, payload: Vec<u8>) -> Option<OperationInput> {
+    OperationInput::from_parts(header.kind, header.step, payload)
+}
+
+/// Length of the kind, step and length prefix of a batch entry.
+const BATCH_ENTRY_PREFIX_LEN: usize = 6;
+
+/// Like [`encode_input`], but packs `inputs` into a single [`OperationKind::Batch`] payload.
+pub fn encode_batch(options: FrameOptions, inputs: Vec<OperationInput>) -> (InputHeader, Vec<u8>) {
+    let mut payload = Vec::new();
+    for input in inputs {
+        let (kind, step, entry) = input.into_parts();
+        payload.push(kind as u8);
+        payload.push(step);
+        payload.extend_from_slice(&(entry.len() as u32).to_le_bytes());
+        payload.extend_from_slice(&entry);
+    }
+    let (mut header, _) = encode_input(options, OperationInput::Noop);
+    header.kind = OperationKind::Batch;
+
+    (header, payload)
+}
+
+/// Rebuilds the operations described by `header`: the entries of a batch, or the single
+/// operation of any other kind. Empty and nested batches and trailing bytes are rejected.
+pub fn decode_inputs(header: &InputHeader, payload: Vec<u8>) -> Option<Vec<OperationInput>> {
+    if header.kind != OperationKind::Batch {
+        return Some(vec![decode_input(header, payload)?]);
+    }
+    if payload.is_empty() {
+        return None;
+    }
+
+    let mut inputs = Vec::new();
+    let mut rest = payload.as_slice();
+    while !rest.is_empty() {
+        if rest.len() < BATCH_ENTRY_PREFIX_LEN {
+            return None;
+        }
+        let kind = OperationKind::from_u8(rest[0])?;
+        let step = rest[1];
+        let len = u32::from_le_bytes(rest[2..BATCH_ENTRY_PREFIX_LEN].try_into().ok()?) as usize;
+        let end = BATCH_ENTRY_PREFIX_LEN.checked_add(len)?;
+        let entry = rest.get(BATCH_ENTRY_PREFIX_LEN..end)?;
+        inputs.push(OperationInput::from_parts(kind, step, entry.to_vec())?);
+        rest = &rest[end..];
+    }
+
+    Some(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::EpochProcessingType;
+
+    fn options() -> FrameOptions {
+        FrameOptions {
+            fork: Fork::Electra,
+            preset: Preset::Mainnet,
+            transport: Transport::Snappy,
+            signatures: SignatureVerification::Ignored,
+            merkleization: Merkleization::Incremental,
+            state_input: StateInput::Full,
+            decoding: Decoding::Eager,
+            stop_after: Phase::Process,
+        }
+    }
+
+    fn header() -> InputHeader {
+        let input = OperationInput::EpochProcessing(EpochProcessingType::Slashings);
+        encode_input(options(), input).0
+    }
+
+    #[test]
+    fn header_round_trips() {
+        let header = header();
+        assert_eq!(InputHeader::from_bytes(&header.to_bytes()), Some(header));
+    }
+
+    #[test]
+    fn header_rejects_other_versions() {
+        for version in [FRAME_VERSION - 1, FRAME_VERSION + 1] {
+            let mut bytes = header().to_bytes();
+            bytes[4] = version;
+            assert_eq!(InputHeader::from_bytes(&bytes), None);
+        }
+    }
+
+    #[test]
+    fn header_rejects_bad_magic_length_and_reserved_byte() {
+        let bytes = header().to_bytes();
+
+        let mut bad_magic = bytes;
+        bad_magic[0] = b'X';
+        assert_eq!(InputHeader::from_bytes(&bad_magic), None);
+
+        assert_eq!(InputHeader::from_bytes(&bytes[..HEADER_LEN - 1]), None);
+
+        let mut reserved = bytes;
+        reserved[15] = 1;
+        assert_eq!(InputHeader::from_bytes(&reserved), None);
+    }
+
+    fn batch_inputs() -> Vec<OperationInput> {
+        vec![
+            OperationInput::Attestation(vec![1, 2, 3]),
+            OperationInput::ProcessSlot,
+            OperationInput::EpochProcessing(EpochProcessingType::Slashings),
+            OperationInput::Deposit(Vec::new()),
+        ]
+    }
+
+    fn parts(inputs: Vec<OperationInput>) -> Vec<(OperationKind, u8, Vec<u8>)> {
+        inputs.into_iter().map(OperationInput::into_parts).collect()
+    }
+
+    #[test]
+    fn batch_round_trips() {
+        let (header, payload) = encode_batch(options(), batch_inputs());
+        assert_eq!(header.kind, OperationKind::Batch);
+        let decoded = decode_inputs(&header, payload).expect("valid batch");
+        assert_eq!(parts(decoded), parts(batch_inputs()));
+    }
+
+    #[test]
+    fn single_input_decodes_as_one_entry() {
+        let input = OperationInput::Attestation(vec![4, 5]);
+        let (header, payload) = encode_input(options(), input.clone());
+        let decoded = decode_inputs(&header, payload).expect("valid input");
+        assert_eq!(parts(decoded), parts(vec![input]));
+    }
+
+    #[test]
+    fn decode_inputs_rejects_truncated_entries() {
+        let (header, payload) = encode_batch(options(), batch_inputs());
+        // Cuts inside the last entry's prefix, then inside the first entry's payload
+        for len in [payload.len() - 1, BATCH_ENTRY_PREFIX_LEN + 1] {
+            assert!(decode_inputs(&header, payload[..len].to_vec()).is_none());
+        }
+    }
+
+    #[test]
+    fn decode_inputs_rejects_trailing_bytes() {
+        let (header, payload) = encode_batch(options(), batch_inputs());
+        for trailing in [vec![0], vec![0; BATCH_ENTRY_PREFIX_LEN - 1]] {
+            let payload = [payload.as_slice(), trailing.as_slice()].concat();
+            assert!(decode_inputs(&header, payload).is_none());
+        }
+        // A full prefix whose length runs past the payload
+        let prefix = [OperationKind::Attestation as u8, 0, 9, 0, 0, 0];
+        let payload = [payload.as_slice(), prefix.as_slice()].concat();
+        assert!(decode_inputs(&header, payload).is_none());
+    }
+
+    #[test]
+    fn decode_inputs_rejects_empty_batch() {
+        let (header, payload) = encode_batch(options(), Vec::new());
+        assert!(payload.is_empty());
+        assert!(decode_inputs(&header, payload).is_none());
+    }
+
+    #[test]
+    fn decode_inputs_rejects_nested_batch() {
+        let (header, inner) = encode_batch(options(), batch_inputs());
+        let mut payload = vec![OperationKind::Batch as u8, 0];
+        payload.extend_from_slice(&(inner.len() as u32).to_le_bytes());
+        payload.extend_from_slice(&inner);
+        assert!(decode_inputs(&header, payload).is_none());
+    }
+}
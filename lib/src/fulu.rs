@@ -0,0 +1,403 @@
+//! Fulu `BeaconState` and its `process_proposer_lookahead` epoch step.
+//!
+//! A Fulu state is the Electra state with a `proposer_lookahead` vector appended. ream only
+//! exposes the Electra state, so [`FuluBeaconState`] splits the SSZ bytes into the Electra fields,
+//! decoded with ream, and the lookahead, and merkleizes both back into the Fulu state root. An
+//! Electra state is upgraded to it with [`FuluBeaconState::upgrade_from_electra`], and Fulu blocks
+//! are applied with [`FuluBeaconState::state_transition`].
+//!
+//! Fulu blocks are processed by ream's Electra `process_block`, which checks the block's proposer
+//! against the proposer it computes from the current effective balances rather than against the
+//! lookahead. A block whose lookahead proposer differs from that computation fails with
+//! [`ProposerDivergence`] instead of being accepted or rejected on the wrong proposer.
+
+use alloy_primitives::B256;
+use anyhow::ensure;
+use ethereum_hashing::hash;
+use ream_bls::traits::Verifiable;
+use ream_consensus::electra::{beacon_block::SignedBeaconBlock, beacon_state::BeaconState};
+use ream_consensus_misc::misc::compute_shuffled_index;
+use ssz::{Decode, DecodeError};
+use tree_hash::{Hash256, TreeHash, merkle_root};
+
+use crate::merkle::{field_roots, root_from_field_roots};
+use crate::spec::{
+    SLOTS_PER_EPOCH, SLOTS_PER_HISTORICAL_ROOT, compute_domain, compute_signing_root,
+};
+use crate::ssz::{BYTES_PER_LENGTH_OFFSET, beacon_state_fixed_lens};
+
+pub const MIN_SEED_LOOKAHEAD: u64 = 1;
+pub const EPOCHS_PER_HISTORICAL_VECTOR: u64 = 65536;
+pub const MAX_EFFECTIVE_BALANCE_ELECTRA: u64 = 2_048_000_000_000;
+pub const DOMAIN_BEACON_PROPOSER: [u8; 4] = [0, 0, 0, 0];
+pub const FULU_FORK_VERSION: [u8; 4] = [6, 0, 0, 0];
+/// Length of `proposer_lookahead`, `(MIN_SEED_LOOKAHEAD + 1) * SLOTS_PER_EPOCH`.
+pub const PROPOSER_LOOKAHEAD_LEN: usize = ((MIN_SEED_LOOKAHEAD + 1) * SLOTS_PER_EPOCH) as usize;
+
+const PROPOSER_LOOKAHEAD_SSZ_LEN: usize = PROPOSER_LOOKAHEAD_LEN * 8;
+const MAX_RANDOM_VALUE: u64 = (1 << 16) - 1;
+
+/// Error of [`FuluBeaconState::state_transition`] for a block whose slot has a lookahead proposer
+/// that ream's Electra `process_block` would not accept.
+#[derive(Debug, thiserror::Error)]
+#[error("lookahead proposer {lookahead} differs from the Electra proposer {electra}")]
+pub struct ProposerDivergence {
+    pub lookahead: u64,
+    pub electra: u64,
+}
+
+/// Fulu `BeaconState`: the Electra fields and the proposer lookahead.
+pub struct FuluBeaconState {
+    pub state: BeaconState,
+    pub proposer_lookahead: Vec<u64>,
+}
+
+impl Decode for FuluBeaconState {
+    fn is_ssz_fixed_len() -> bool {
+        false
+    }
+
+    /// Decodes a Fulu state by removing `proposer_lookahead`, the last fixed-size field, and
+    /// shifting the offsets of the variable-size fields back over it.
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let fixed_lens = beacon_state_fixed_lens();
+        let electra_fixed_len: usize = fixed_lens
+            .iter()
+            .map(|fixed_len| fixed_len.unwrap_or(BYTES_PER_LENGTH_OFFSET))
+            .sum();
+        let lookahead_end = electra_fixed_len + PROPOSER_LOOKAHEAD_SSZ_LEN;
+        let lookahead_bytes =
+            bytes
+                .get(electra_fixed_len..lookahead_end)
+                .ok_or(DecodeError::InvalidByteLength {
+                    len: bytes.len(),
+                    expected: lookahead_end,
+                })?;
+
+        let mut electra_bytes = Vec::with_capacity(bytes.len() - PROPOSER_LOOKAHEAD_SSZ_LEN);
+        electra_bytes.extend_from_slice(&bytes[..electra_fixed_len]);
+        electra_bytes.extend_from_slice(&bytes[lookahead_end..]);
+        let mut position = 0;
+        for fixed_len in fixed_lens {
+            if fixed_len.is_none() {
+                let slot = &mut electra_bytes[position..position + BYTES_PER_LENGTH_OFFSET];
+                let offset = u32::from_le_bytes(slot[..].try_into().unwrap()) as usize;
+                let shifted = offset
+                    .checked_sub(PROPOSER_LOOKAHEAD_SSZ_LEN)
+                    .ok_or(DecodeError::OffsetIntoFixedPortion(offset))?;
+                slot.copy_from_slice(&(shifted as u32).to_le_bytes());
+            }
+            position += fixed_len.unwrap_or(BYTES_PER_LENGTH_OFFSET);
+        }
+
+        Ok(Self {
+            state: BeaconState::from_ssz_bytes(&electra_bytes)?,
+            proposer_lookahead: lookahead_bytes
+                .chunks_exact(8)
+                .map(|index| u64::from_le_bytes(index.try_into().unwrap()))
+                .collect(),
+        })
+    }
+}
+
+impl FuluBeaconState {
+    /// `upgrade_to_fulu`: keeps every Electra field, moves the fork to Fulu at the state's current
+    /// epoch and initializes the proposer lookahead of that epoch and the next
+    /// `MIN_SEED_LOOKAHEAD`.
+    pub fn upgrade_from_electra(mut state: BeaconState) -> anyhow::Result<Self> {
+        let epoch = state.get_current_epoch();
+        state.fork.previous_version = state.fork.current_version;
+        state.fork.current_version = FULU_FORK_VERSION.into();
+        state.fork.epoch = epoch;
+
+        let mut fulu_state = Self {
+            state,
+            proposer_lookahead: Vec::with_capacity(PROPOSER_LOOKAHEAD_LEN),
+        };
+        for lookahead_epoch in epoch..=epoch + MIN_SEED_LOOKAHEAD {
+            let proposers = fulu_state.get_beacon_proposer_indices(lookahead_epoch)?;
+            fulu_state.proposer_lookahead.extend(proposers);
+        }
+
+        Ok(fulu_state)
+    }
+
+    /// Root of the Fulu state: the Electra field roots followed by the lookahead's root.
+    pub fn tree_hash_root(&self) -> Hash256 {
+        let lookahead_bytes: Vec<u8> = self
+            .proposer_lookahead
+            .iter()
+            .flat_map(|index| index.to_le_bytes())
+            .collect();
+        let mut roots = field_roots(&self.state);
+        roots.push(merkle_root(&lookahead_bytes, 0));
+
+        root_from_field_roots(&roots)
+    }
+
+    /// Fulu `state_transition`. The block itself is processed by ream's Electra `process_block`,
+    /// since Fulu leaves block processing unchanged apart from reading the proposer from the
+    /// lookahead. The proposer is checked against the lookahead first, and a slot whose lookahead
+    /// proposer is not the one ream computes fails with [`ProposerDivergence`].
+    pub fn state_transition(
+        &mut self,
+        signed_block: &SignedBeaconBlock,
+        validate_result: bool,
+    ) -> anyhow::Result<()> {
+        let block = &signed_block.message;
+        self.process_slots(block.slot)?;
+        let lookahead_proposer = self.proposer_lookahead[(block.slot % SLOTS_PER_EPOCH) as usize];
+        ensure!(
+            block.proposer_index == lookahead_proposer,
+            "block proposer is not the lookahead proposer"
+        );
+        let electra_proposer = self.electra_beacon_proposer_index()?;
+        if electra_proposer != lookahead_proposer {
+            return Err(ProposerDivergence {
+                lookahead: lookahead_proposer,
+                electra: electra_proposer,
+            }
+            .into());
+        }
+        if validate_result {
+            ensure!(
+                self.verify_block_signature(signed_block),
+                "invalid block signature"
+            );
+        }
+        self.state.process_block(block)?;
+        if validate_result {
+            ensure!(
+                block.state_root == self.tree_hash_root(),
+                "block state root does not match"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// `process_slots`, with `process_proposer_lookahead` at the end of every epoch.
+    pub fn process_slots(&mut self, slot: u64) -> anyhow::Result<()> {
+        ensure!(
+            self.state.slot < slot,
+            "slot {slot} is not after the state's slot"
+        );
+        while self.state.slot < slot {
+            self.process_slot();
+            if (self.state.slot + 1) % SLOTS_PER_EPOCH == 0 {
+                self.state.process_epoch()?;
+                self.process_proposer_lookahead()?;
+            }
+            self.state.slot += 1;
+        }
+
+        Ok(())
+    }
+
+    /// `process_slot`, caching the Fulu state root rather than the root of the Electra fields.
+    fn process_slot(&mut self) {
+        let index = (self.state.slot % SLOTS_PER_HISTORICAL_ROOT) as usize;
+
+        // Cache state root
+        let previous_state_root = self.tree_hash_root();
+        self.state.state_roots[index] = previous_state_root;
+
+        // Cache latest block header state root
+        if self.state.latest_block_header.state_root == Hash256::ZERO {
+            self.state.latest_block_header.state_root = previous_state_root;
+        }
+
+        // Cache block root
+        self.state.block_roots[index] = self.state.latest_block_header.tree_hash_root();
+    }
+
+    /// `verify_block_signature`, against the proposer the block names.
+    fn verify_block_signature(&self, signed_block: &SignedBeaconBlock) -> bool {
+        let block = &signed_block.message;
+        let Some(proposer) = self.state.validators.get(block.proposer_index as usize) else {
+            return false;
+        };
+        let fork = &self.state.fork;
+        let fork_version = if block.slot / SLOTS_PER_EPOCH < fork.epoch {
+            fork.previous_version
+        } else {
+            fork.current_version
+        };
+        let domain = compute_domain(
+            DOMAIN_BEACON_PROPOSER,
+            fork_version.0,
+            self.state.genesis_validators_root,
+        );
+        let signing_root = compute_signing_root(block.tree_hash_root(), domain);
+
+        signed_block
+            .signature
+            .verify(&proposer.pubkey, signing_root.as_slice())
+            .unwrap_or(false)
+    }
+
+    /// `process_proposer_lookahead`: shifts out the proposers of the current epoch and fills in
+    /// those of the epoch `MIN_SEED_LOOKAHEAD + 1` ahead.
+    pub fn process_proposer_lookahead(&mut self) -> anyhow::Result<()> {
+        let slots_per_epoch = SLOTS_PER_EPOCH as usize;
+        let last_epoch_start = PROPOSER_LOOKAHEAD_LEN - slots_per_epoch;
+        self.proposer_lookahead.copy_within(slots_per_epoch.., 0);
+        let epoch = self.state.get_current_epoch() + MIN_SEED_LOOKAHEAD + 1;
+        let last_epoch_proposers = self.get_beacon_proposer_indices(epoch)?;
+        self.proposer_lookahead[last_epoch_start..].copy_from_slice(&last_epoch_proposers);
+
+        Ok(())
+    }
+
+    /// `get_beacon_proposer_indices`: the proposer of every slot of `epoch`.
+    fn get_beacon_proposer_indices(&self, epoch: u64) -> anyhow::Result<Vec<u64>> {
+        let indices = self.active_validator_indices(epoch);
+        let seed = self.get_seed(epoch, DOMAIN_BEACON_PROPOSER);
+        let start_slot = epoch * SLOTS_PER_EPOCH;
+
+        (start_slot..start_slot + SLOTS_PER_EPOCH)
+            .map(|slot| self.compute_proposer_index(&indices, proposer_seed(seed, slot)))
+            .collect()
+    }
+
+    /// Electra `get_beacon_proposer_index`: the proposer of the state's slot, from the current
+    /// effective balances.
+    fn electra_beacon_proposer_index(&self) -> anyhow::Result<u64> {
+        let epoch = self.state.get_current_epoch();
+        let indices = self.active_validator_indices(epoch);
+        let seed = self.get_seed(epoch, DOMAIN_BEACON_PROPOSER);
+
+        self.compute_proposer_index(&indices, proposer_seed(seed, self.state.slot))
+    }
+
+    fn active_validator_indices(&self, epoch: u64) -> Vec<u64> {
+        self.state
+            .validators
+            .iter()
+            .enumerate()
+            .filter(|(_, validator)| {
+                validator.activation_epoch <= epoch && epoch < validator.exit_epoch
+            })
+            .map(|(index, _)| index as u64)
+            .collect()
+    }
+
+    /// `get_seed`, from the RANDAO mix `MIN_SEED_LOOKAHEAD + 1` epochs before `epoch`.
+    fn get_seed(&self, epoch: u64, domain_type: [u8; 4]) -> B256 {
+        let mix_epoch = epoch + EPOCHS_PER_HISTORICAL_VECTOR - MIN_SEED_LOOKAHEAD - 1;
+        let mix = self.state.randao_mixes[(mix_epoch % EPOCHS_PER_HISTORICAL_VECTOR) as usize];
+
+        B256::from_slice(&hash(
+            &[domain_type.as_slice(), &epoch.to_le_bytes(), mix.as_slice()].concat(),
+        ))
+    }
+
+    /// Electra `compute_proposer_index`: samples candidates from the shuffled `indices` with a
+    /// probability proportional to their effective balance.
+    fn compute_proposer_index(&self, indices: &[u64], seed: B256) -> anyhow::Result<u64> {
+        ensure!(!indices.is_empty(), "no active validator to propose");
+        let total = indices.len();
+        let mut i = 0;
+        loop {
+            let candidate_index = indices[compute_shuffled_index(i % total, total, seed)?];
+            let random_bytes = hash(&[seed.as_slice(), &((i / 16) as u64).to_le_bytes()].concat());
+            let offset = i % 16 * 2;
+            let random_value =
+                u16::from_le_bytes([random_bytes[offset], random_bytes[offset + 1]]) as u64;
+            let effective_balance =
+                self.state.validators[candidate_index as usize].effective_balance;
+            if effective_balance * MAX_RANDOM_VALUE >= MAX_EFFECTIVE_BALANCE_ELECTRA * random_value
+            {
+                return Ok(candidate_index);
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Seed of the proposer of `slot`, from the epoch's proposer seed.
+fn proposer_seed(epoch_seed: B256, slot: u64) -> B256 {
+    B256::from_slice(&hash(
+        &[epoch_seed.as_slice(), &slot.to_le_bytes()].concat(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use ream_consensus::validator::Validator;
+
+    use super::*;
+
+    const ELECTRA_FORK_VERSION: [u8; 4] = [5, 0, 0, 0];
+
+    /// An Electra state at `epoch` with `count` active validators of increasing balances.
+    fn electra_state(epoch: u64, count: u64) -> BeaconState {
+        let mut state = BeaconState::default();
+        state.slot = epoch * SLOTS_PER_EPOCH + 3;
+        state.fork.current_version = ELECTRA_FORK_VERSION.into();
+        for index in 0..count {
+            let validator = Validator {
+                effective_balance: MAX_EFFECTIVE_BALANCE_ELECTRA / (index + 1),
+                activation_epoch: 0,
+                exit_epoch: u64::MAX,
+                ..Validator::default()
+            };
+            state.validators.push(validator).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn upgrade_from_electra_moves_fork_and_fills_two_epochs() {
+        let epoch = 5;
+        let state = FuluBeaconState::upgrade_from_electra(electra_state(epoch, 8)).unwrap();
+
+        assert_eq!(
+            state.state.fork.previous_version,
+            ELECTRA_FORK_VERSION.into()
+        );
+        assert_eq!(state.state.fork.current_version, FULU_FORK_VERSION.into());
+        assert_eq!(state.state.fork.epoch, epoch);
+        assert_eq!(state.proposer_lookahead.len(), PROPOSER_LOOKAHEAD_LEN);
+        let slots_per_epoch = SLOTS_PER_EPOCH as usize;
+        assert_eq!(
+            state.proposer_lookahead[..slots_per_epoch],
+            state.get_beacon_proposer_indices(epoch).unwrap()[..]
+        );
+        assert_eq!(
+            state.proposer_lookahead[slots_per_epoch..],
+            state.get_beacon_proposer_indices(epoch + 1).unwrap()[..]
+        );
+        assert!(state.proposer_lookahead.iter().all(|index| *index < 8));
+    }
+
+    #[test]
+    fn process_proposer_lookahead_shifts_in_the_epoch_after_next() {
+        let epoch = 5;
+        let mut state = FuluBeaconState::upgrade_from_electra(electra_state(epoch, 8)).unwrap();
+        let previous_lookahead = state.proposer_lookahead.clone();
+
+        state.process_proposer_lookahead().unwrap();
+
+        let slots_per_epoch = SLOTS_PER_EPOCH as usize;
+        assert_eq!(
+            state.proposer_lookahead[..slots_per_epoch],
+            previous_lookahead[slots_per_epoch..]
+        );
+        assert_eq!(
+            state.proposer_lookahead[slots_per_epoch..],
+            state.get_beacon_proposer_indices(epoch + 2).unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn lookahead_of_the_current_epoch_matches_the_electra_proposer() {
+        let state = FuluBeaconState::upgrade_from_electra(electra_state(5, 8)).unwrap();
+        let slot_index = (state.state.slot % SLOTS_PER_EPOCH) as usize;
+
+        assert_eq!(
+            state.electra_beacon_proposer_index().unwrap(),
+            state.proposer_lookahead[slot_index]
+        );
+    }
+}
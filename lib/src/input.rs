@@ -1,6 +1,11 @@
+use alloy_primitives::B256;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug)]
+use crate::light_client::ForkSchedule;
+use crate::ssz_static::SszType;
+use crate::state_query::ValidatorField;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum OperationInput {
     Attestation(Vec<u8>),
     AttesterSlashing(Vec<u8>),
@@ -14,9 +19,72 @@ pub enum OperationInput {
     ExecutionPayload(Vec<u8>),
     EpochProcessing(EpochProcessingType),
     ProcessSlot,
+    /// Leaves the state untouched, to measure the fixed cost of reading, decoding and
+    /// merkleizing it.
+    Noop,
+    /// Upgrades a state of the previous fork to the fork named in the frame header. The
+    /// pre-state buffer then holds the previous fork's `BeaconState`.
+    UpgradeToFork,
+    /// Full state transition of a `SignedBeaconBlock` of the fork named in the frame header.
+    SignedBeaconBlock(Vec<u8>),
+    /// Full state transition of a `SignedBeaconBlock` of the previous fork, applied to a state of
+    /// the previous fork.
+    PreForkSignedBeaconBlock(Vec<u8>),
+    /// Processes slots of a previous fork's state up to the start of the given epoch and
+    /// upgrades it there, as transition cases do at their `fork_epoch`.
+    UpgradeAtEpoch(u64),
+    /// Computes the reward and penalty deltas of the pre-state without applying them, see
+    /// [`crate::rewards`].
+    RewardsDeltas,
+    /// Decodes and hashes a standalone container, without any state.
+    SszStatic(SszType, Vec<u8>),
+    /// Shuffles every index of `0..count` for `seed`, without any state, see
+    /// [`crate::shuffling`].
+    Shuffle { seed: B256, count: u64 },
+    /// SSZ `BeaconBlock` of the pre-state, starting a fork choice store. The steps of the case
+    /// follow it in the same batch, see [`crate::fork_choice`].
+    ForkChoiceAnchor(Vec<u8>),
+    /// `on_tick` with the store time in seconds.
+    OnTick(u64),
+    /// `on_block` with an SSZ `SignedBeaconBlock`, and whether the spec expects it to be accepted.
+    OnBlock { block: Vec<u8>, valid: bool },
+    /// `on_attestation` with an SSZ `Attestation`, and whether the spec expects it to be accepted.
+    OnAttestation { attestation: Vec<u8>, valid: bool },
+    /// `on_attester_slashing` with an SSZ `AttesterSlashing`, and whether the spec expects it to
+    /// be accepted.
+    OnAttesterSlashing { attester_slashing: Vec<u8>, valid: bool },
+    /// Records a [`crate::fork_choice::StoreSnapshot`] of the store.
+    ForkChoiceChecks,
+    /// Initializes a light client store from the SSZ `LightClientBootstrap` in the pre-state
+    /// buffer. The steps of the case follow it in the same batch, see [`crate::light_client`].
+    LightClientBootstrap {
+        trusted_block_root: B256,
+        genesis_validators_root: B256,
+        fork_schedule: ForkSchedule,
+    },
+    /// `process_light_client_update` with an SSZ `LightClientUpdate`.
+    LightClientUpdate { update: Vec<u8>, current_slot: u64 },
+    /// `process_light_client_store_force_update` at the given slot.
+    LightClientForceUpdate(u64),
+    /// SSZ `LightClientUpdate` of an update ranking case, whose updates are listed best first.
+    RankedLightClientUpdate(Vec<u8>),
+    /// Branch of `leaf` at generalized index `gindex` in the SSZ object of type `ssz_type` in the
+    /// pre-state buffer, see [`crate::merkle_proof`].
+    MerkleProof {
+        ssz_type: SszType,
+        leaf: B256,
+        gindex: u64,
+        branch: Vec<B256>,
+    },
+    /// `field` of the validators at `indices` in the pre-state, see [`crate::state_query`].
+    StateQuery {
+        field: ValidatorField,
+        indices: Vec<u64>,
+    },
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
 pub enum EpochProcessingType {
     JustificationAndFinalization,
     InactivityUpdates,
@@ -32,4 +100,480 @@ pub enum EpochProcessingType {
     HistoricalSummariesUpdate,
     ParticipationFlagUpdates,
     SyncCommitteeUpdates,
+    /// The whole `process_epoch`, running every step above in spec order.
+    Full,
+    /// `process_proposer_lookahead`, added in Fulu.
+    ProposerLookahead,
+}
+
+/// Payload-free tag of an [`OperationInput`], as carried in the input frame header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OperationKind {
+    Attestation,
+    AttesterSlashing,
+    BeaconBlock,
+    SignedBLSToExecutionChange,
+    Deposit,
+    BeaconBlockBody,
+    ProposerSlashing,
+    SyncAggregate,
+    SignedVoluntaryExit,
+    ExecutionPayload,
+    EpochProcessing,
+    ProcessSlot,
+    Noop,
+    /// Several inputs applied in order, see [`crate::frame::encode_batch`].
+    Batch,
+    UpgradeToFork,
+    SignedBeaconBlock,
+    PreForkSignedBeaconBlock,
+    UpgradeAtEpoch,
+    RewardsDeltas,
+    SszStatic,
+    Shuffle,
+    ForkChoiceAnchor,
+    OnTick,
+    OnBlock,
+    OnAttestation,
+    OnAttesterSlashing,
+    ForkChoiceChecks,
+    LightClientBootstrap,
+    LightClientUpdate,
+    LightClientForceUpdate,
+    RankedLightClientUpdate,
+    MerkleProof,
+    StateQuery,
+}
+
+impl OperationKind {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(OperationKind::Attestation),
+            1 => Some(OperationKind::AttesterSlashing),
+            2 => Some(OperationKind::BeaconBlock),
+            3 => Some(OperationKind::SignedBLSToExecutionChange),
+            4 => Some(OperationKind::Deposit),
+            5 => Some(OperationKind::BeaconBlockBody),
+            6 => Some(OperationKind::ProposerSlashing),
+            7 => Some(OperationKind::SyncAggregate),
+            8 => Some(OperationKind::SignedVoluntaryExit),
+            9 => Some(OperationKind::ExecutionPayload),
+            10 => Some(OperationKind::EpochProcessing),
+            11 => Some(OperationKind::ProcessSlot),
+            12 => Some(OperationKind::Noop),
+            13 => Some(OperationKind::Batch),
+            14 => Some(OperationKind::UpgradeToFork),
+            15 => Some(OperationKind::SignedBeaconBlock),
+            16 => Some(OperationKind::PreForkSignedBeaconBlock),
+            17 => Some(OperationKind::UpgradeAtEpoch),
+            18 => Some(OperationKind::RewardsDeltas),
+            19 => Some(OperationKind::SszStatic),
+            20 => Some(OperationKind::Shuffle),
+            21 => Some(OperationKind::ForkChoiceAnchor),
+            22 => Some(OperationKind::OnTick),
+            23 => Some(OperationKind::OnBlock),
+            24 => Some(OperationKind::OnAttestation),
+            25 => Some(OperationKind::OnAttesterSlashing),
+            26 => Some(OperationKind::ForkChoiceChecks),
+            27 => Some(OperationKind::LightClientBootstrap),
+            28 => Some(OperationKind::LightClientUpdate),
+            29 => Some(OperationKind::LightClientForceUpdate),
+            30 => Some(OperationKind::RankedLightClientUpdate),
+            31 => Some(OperationKind::MerkleProof),
+            32 => Some(OperationKind::StateQuery),
+            _ => None,
+        }
+    }
+}
+
+impl EpochProcessingType {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(EpochProcessingType::JustificationAndFinalization),
+            1 => Some(EpochProcessingType::InactivityUpdates),
+            2 => Some(EpochProcessingType::RewardsAndPenalties),
+            3 => Some(EpochProcessingType::RegistryUpdates),
+            4 => Some(EpochProcessingType::Slashings),
+            5 => Some(EpochProcessingType::Eth1DataReset),
+            6 => Some(EpochProcessingType::PendingDeposits),
+            7 => Some(EpochProcessingType::PendingConsolidations),
+            8 => Some(EpochProcessingType::EffectiveBalanceUpdates),
+            9 => Some(EpochProcessingType::SlashingsReset),
+            10 => Some(EpochProcessingType::RandaoMixesReset),
+            11 => Some(EpochProcessingType::HistoricalSummariesUpdate),
+            12 => Some(EpochProcessingType::ParticipationFlagUpdates),
+            13 => Some(EpochProcessingType::SyncCommitteeUpdates),
+            14 => Some(EpochProcessingType::Full),
+            15 => Some(EpochProcessingType::ProposerLookahead),
+            _ => None,
+        }
+    }
+}
+
+impl OperationInput {
+    /// Whether the input starts from a state of the fork preceding the frame header's fork.
+    pub fn needs_previous_fork_state(&self) -> bool {
+        matches!(
+            self,
+            OperationInput::UpgradeToFork
+                | OperationInput::PreForkSignedBeaconBlock(_)
+                | OperationInput::UpgradeAtEpoch(_)
+        )
+    }
+
+    /// Splits the input into its header tag, epoch processing step, [`SszType`] or expected
+    /// validity of a fork choice step (0 when not applicable) and raw SSZ payload (empty when the
+    /// operation has none).
+    pub fn into_parts(self) -> (OperationKind, u8, Vec<u8>) {
+        match self {
+            OperationInput::Attestation(ssz_bytes) => (OperationKind::Attestation, 0, ssz_bytes),
+            OperationInput::AttesterSlashing(ssz_bytes) => {
+                (OperationKind::AttesterSlashing, 0, ssz_bytes)
+            }
+            OperationInput::BeaconBlock(ssz_bytes) => (OperationKind::BeaconBlock, 0, ssz_bytes),
+            OperationInput::SignedBLSToExecutionChange(ssz_bytes) => {
+                (OperationKind::SignedBLSToExecutionChange, 0, ssz_bytes)
+            }
+            OperationInput::Deposit(ssz_bytes) => (OperationKind::Deposit, 0, ssz_bytes),
+            OperationInput::BeaconBlockBody(ssz_bytes) => {
+                (OperationKind::BeaconBlockBody, 0, ssz_bytes)
+            }
+            OperationInput::ProposerSlashing(ssz_bytes) => {
+                (OperationKind::ProposerSlashing, 0, ssz_bytes)
+            }
+            OperationInput::SyncAggregate(ssz_bytes) => {
+                (OperationKind::SyncAggregate, 0, ssz_bytes)
+            }
+            OperationInput::SignedVoluntaryExit(ssz_bytes) => {
+                (OperationKind::SignedVoluntaryExit, 0, ssz_bytes)
+            }
+            OperationInput::ExecutionPayload(ssz_bytes) => {
+                (OperationKind::ExecutionPayload, 0, ssz_bytes)
+            }
+            OperationInput::EpochProcessing(epoch_type) => {
+                (OperationKind::EpochProcessing, epoch_type as u8, Vec::new())
+            }
+            OperationInput::ProcessSlot => (OperationKind::ProcessSlot, 0, Vec::new()),
+            OperationInput::Noop => (OperationKind::Noop, 0, Vec::new()),
+            OperationInput::UpgradeToFork => (OperationKind::UpgradeToFork, 0, Vec::new()),
+            OperationInput::SignedBeaconBlock(ssz_bytes) => {
+                (OperationKind::SignedBeaconBlock, 0, ssz_bytes)
+            }
+            OperationInput::PreForkSignedBeaconBlock(ssz_bytes) => {
+                (OperationKind::PreForkSignedBeaconBlock, 0, ssz_bytes)
+            }
+            OperationInput::UpgradeAtEpoch(epoch) => {
+                (OperationKind::UpgradeAtEpoch, 0, epoch.to_le_bytes().to_vec())
+            }
+            OperationInput::RewardsDeltas => (OperationKind::RewardsDeltas, 0, Vec::new()),
+            OperationInput::SszStatic(ssz_type, ssz_bytes) => {
+                (OperationKind::SszStatic, ssz_type as u8, ssz_bytes)
+            }
+            OperationInput::Shuffle { seed, count } => {
+                let mut payload = seed.to_vec();
+                payload.extend_from_slice(&count.to_le_bytes());
+                (OperationKind::Shuffle, 0, payload)
+            }
+            OperationInput::ForkChoiceAnchor(ssz_bytes) => {
+                (OperationKind::ForkChoiceAnchor, 0, ssz_bytes)
+            }
+            OperationInput::OnTick(time) => {
+                (OperationKind::OnTick, 0, time.to_le_bytes().to_vec())
+            }
+            OperationInput::OnBlock { block, valid } => (OperationKind::OnBlock, valid as u8, block),
+            OperationInput::OnAttestation { attestation, valid } => {
+                (OperationKind::OnAttestation, valid as u8, attestation)
+            }
+            OperationInput::OnAttesterSlashing {
+                attester_slashing,
+                valid,
+            } => (OperationKind::OnAttesterSlashing, valid as u8, attester_slashing),
+            OperationInput::ForkChoiceChecks => (OperationKind::ForkChoiceChecks, 0, Vec::new()),
+            OperationInput::LightClientBootstrap {
+                trusted_block_root,
+                genesis_validators_root,
+                fork_schedule,
+            } => {
+                let mut payload = trusted_block_root.to_vec();
+                payload.extend_from_slice(genesis_validators_root.as_slice());
+                for (epoch, fork_version) in fork_schedule {
+                    payload.extend_from_slice(&epoch.to_le_bytes());
+                    payload.extend_from_slice(&fork_version);
+                }
+                (OperationKind::LightClientBootstrap, 0, payload)
+            }
+            OperationInput::LightClientUpdate {
+                mut update,
+                current_slot,
+            } => {
+                update.extend_from_slice(&current_slot.to_le_bytes());
+                (OperationKind::LightClientUpdate, 0, update)
+            }
+            OperationInput::LightClientForceUpdate(current_slot) => (
+                OperationKind::LightClientForceUpdate,
+                0,
+                current_slot.to_le_bytes().to_vec(),
+            ),
+            OperationInput::RankedLightClientUpdate(ssz_bytes) => {
+                (OperationKind::RankedLightClientUpdate, 0, ssz_bytes)
+            }
+            OperationInput::MerkleProof {
+                ssz_type,
+                leaf,
+                gindex,
+                branch,
+            } => {
+                let mut payload = leaf.to_vec();
+                payload.extend_from_slice(&gindex.to_le_bytes());
+                payload.extend(branch.iter().flat_map(|node| node.0));
+                (OperationKind::MerkleProof, ssz_type as u8, payload)
+            }
+            OperationInput::StateQuery { field, indices } => {
+                let payload = indices.iter().flat_map(|index| index.to_le_bytes()).collect();
+                (OperationKind::StateQuery, field as u8, payload)
+            }
+        }
+    }
+
+    /// Rebuilds the input from the parts produced by [`OperationInput::into_parts`], taking
+    /// ownership of `payload` so that no bytes are copied. A [`OperationKind::Batch`] is not a
+    /// single input and yields `None`.
+    pub fn from_parts(kind: OperationKind, step: u8, payload: Vec<u8>) -> Option<Self> {
+        let input = match kind {
+            OperationKind::Attestation => OperationInput::Attestation(payload),
+            OperationKind::AttesterSlashing => OperationInput::AttesterSlashing(payload),
+            OperationKind::BeaconBlock => OperationInput::BeaconBlock(payload),
+            OperationKind::SignedBLSToExecutionChange => {
+                OperationInput::SignedBLSToExecutionChange(payload)
+            }
+            OperationKind::Deposit => OperationInput::Deposit(payload),
+            OperationKind::BeaconBlockBody => OperationInput::BeaconBlockBody(payload),
+            OperationKind::ProposerSlashing => OperationInput::ProposerSlashing(payload),
+            OperationKind::SyncAggregate => OperationInput::SyncAggregate(payload),
+            OperationKind::SignedVoluntaryExit => OperationInput::SignedVoluntaryExit(payload),
+            OperationKind::ExecutionPayload => OperationInput::ExecutionPayload(payload),
+            OperationKind::EpochProcessing => {
+                OperationInput::EpochProcessing(EpochProcessingType::from_u8(step)?)
+            }
+            OperationKind::ProcessSlot => OperationInput::ProcessSlot,
+            OperationKind::Noop => OperationInput::Noop,
+            OperationKind::UpgradeToFork => OperationInput::UpgradeToFork,
+            OperationKind::SignedBeaconBlock => OperationInput::SignedBeaconBlock(payload),
+            OperationKind::PreForkSignedBeaconBlock => {
+                OperationInput::PreForkSignedBeaconBlock(payload)
+            }
+            OperationKind::UpgradeAtEpoch => {
+                OperationInput::UpgradeAtEpoch(u64::from_le_bytes(payload.try_into().ok()?))
+            }
+            OperationKind::RewardsDeltas => OperationInput::RewardsDeltas,
+            OperationKind::SszStatic => OperationInput::SszStatic(SszType::from_u8(step)?, payload),
+            OperationKind::Shuffle => {
+                // The 32-byte seed followed by the count as a little-endian `u64`
+                if payload.len() != 40 {
+                    return None;
+                }
+                let (seed, count) = payload.split_at(32);
+                OperationInput::Shuffle {
+                    seed: B256::from_slice(seed),
+                    count: u64::from_le_bytes(count.try_into().ok()?),
+                }
+            }
+            OperationKind::ForkChoiceAnchor => OperationInput::ForkChoiceAnchor(payload),
+            OperationKind::OnTick => {
+                OperationInput::OnTick(u64::from_le_bytes(payload.try_into().ok()?))
+            }
+            OperationKind::OnBlock => OperationInput::OnBlock {
+                block: payload,
+                valid: step != 0,
+            },
+            OperationKind::OnAttestation => OperationInput::OnAttestation {
+                attestation: payload,
+                valid: step != 0,
+            },
+            OperationKind::OnAttesterSlashing => OperationInput::OnAttesterSlashing {
+                attester_slashing: payload,
+                valid: step != 0,
+            },
+            OperationKind::ForkChoiceChecks => OperationInput::ForkChoiceChecks,
+            OperationKind::LightClientBootstrap => {
+                // The trusted block root, the genesis validators root and at least one fork, as a
+                // little-endian `u64` epoch followed by the fork version
+                let forks = payload.get(64..)?;
+                if forks.is_empty() || forks.len() % 12 != 0 {
+                    return None;
+                }
+                let fork_schedule = forks
+                    .chunks_exact(12)
+                    .map(|fork| {
+                        let epoch = u64::from_le_bytes(fork[..8].try_into().ok()?);
+                        Some((epoch, fork[8..].try_into().ok()?))
+                    })
+                    .collect::<Option<_>>()?;
+                OperationInput::LightClientBootstrap {
+                    trusted_block_root: B256::from_slice(&payload[..32]),
+                    genesis_validators_root: B256::from_slice(&payload[32..64]),
+                    fork_schedule,
+                }
+            }
+            OperationKind::LightClientUpdate => {
+                // The update, followed by the current slot as a little-endian `u64`
+                let mut update = payload;
+                let slot_offset = update.len().checked_sub(8)?;
+                let current_slot = update.split_off(slot_offset);
+                let current_slot = u64::from_le_bytes(current_slot.try_into().ok()?);
+                OperationInput::LightClientUpdate {
+                    update,
+                    current_slot,
+                }
+            }
+            OperationKind::LightClientForceUpdate => {
+                OperationInput::LightClientForceUpdate(u64::from_le_bytes(payload.try_into().ok()?))
+            }
+            OperationKind::RankedLightClientUpdate => {
+                OperationInput::RankedLightClientUpdate(payload)
+            }
+            OperationKind::MerkleProof => {
+                // The leaf, the generalized index as a little-endian `u64`, then the branch
+                if payload.len() < 40 || (payload.len() - 40) % 32 != 0 {
+                    return None;
+                }
+                OperationInput::MerkleProof {
+                    ssz_type: SszType::from_u8(step)?,
+                    leaf: B256::from_slice(&payload[..32]),
+                    gindex: u64::from_le_bytes(payload[32..40].try_into().ok()?),
+                    branch: payload[40..].chunks_exact(32).map(B256::from_slice).collect(),
+                }
+            }
+            OperationKind::StateQuery => {
+                // The validator indices as little-endian `u64`s
+                if payload.len() % 8 != 0 {
+                    return None;
+                }
+                OperationInput::StateQuery {
+                    field: ValidatorField::from_u8(step)?,
+                    indices: payload
+                        .chunks_exact(8)
+                        .map(|index| u64::from_le_bytes(index.try_into().unwrap()))
+                        .collect(),
+                }
+            }
+            OperationKind::Batch => return None,
+        };
+
+        Some(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One input of every [`OperationKind`] but [`OperationKind::Batch`].
+    fn inputs() -> Vec<OperationInput> {
+        let bytes = vec![1, 2, 3];
+        vec![
+            OperationInput::Attestation(bytes.clone()),
+            OperationInput::AttesterSlashing(bytes.clone()),
+            OperationInput::BeaconBlock(bytes.clone()),
+            OperationInput::SignedBLSToExecutionChange(bytes.clone()),
+            OperationInput::Deposit(bytes.clone()),
+            OperationInput::BeaconBlockBody(bytes.clone()),
+            OperationInput::ProposerSlashing(bytes.clone()),
+            OperationInput::SyncAggregate(bytes.clone()),
+            OperationInput::SignedVoluntaryExit(bytes.clone()),
+            OperationInput::ExecutionPayload(bytes.clone()),
+            OperationInput::EpochProcessing(EpochProcessingType::ProposerLookahead),
+            OperationInput::ProcessSlot,
+            OperationInput::Noop,
+            OperationInput::UpgradeToFork,
+            OperationInput::SignedBeaconBlock(bytes.clone()),
+            OperationInput::PreForkSignedBeaconBlock(bytes.clone()),
+            OperationInput::UpgradeAtEpoch(12),
+            OperationInput::RewardsDeltas,
+            OperationInput::SszStatic(SszType::Deposit, bytes.clone()),
+            OperationInput::Shuffle {
+                seed: B256::repeat_byte(7),
+                count: 100,
+            },
+            OperationInput::ForkChoiceAnchor(bytes.clone()),
+            OperationInput::OnTick(3),
+            OperationInput::OnBlock {
+                block: bytes.clone(),
+                valid: false,
+            },
+            OperationInput::OnAttestation {
+                attestation: bytes.clone(),
+                valid: true,
+            },
+            OperationInput::OnAttesterSlashing {
+                attester_slashing: bytes.clone(),
+                valid: false,
+            },
+            OperationInput::ForkChoiceChecks,
+            OperationInput::LightClientBootstrap {
+                trusted_block_root: B256::repeat_byte(1),
+                genesis_validators_root: B256::repeat_byte(2),
+                fork_schedule: vec![(0, [4, 0, 0, 0]), (364032, [5, 0, 0, 0])],
+            },
+            OperationInput::LightClientUpdate {
+                update: bytes.clone(),
+                current_slot: 9,
+            },
+            OperationInput::LightClientForceUpdate(5),
+            OperationInput::RankedLightClientUpdate(bytes.clone()),
+            OperationInput::MerkleProof {
+                ssz_type: SszType::BeaconState,
+                leaf: B256::repeat_byte(3),
+                gindex: 105,
+                branch: vec![B256::repeat_byte(4), B256::repeat_byte(5)],
+            },
+            OperationInput::StateQuery {
+                field: ValidatorField::Status,
+                indices: vec![0, 7],
+            },
+        ]
+    }
+
+    #[test]
+    fn parts_round_trip_for_every_kind() {
+        let mut kinds = Vec::new();
+        for input in inputs() {
+            let parts = input.into_parts();
+            let (kind, step, payload) = parts.clone();
+            let round_trip = OperationInput::from_parts(kind, step, payload)
+                .unwrap_or_else(|| panic!("{kind:?} does not decode its own parts"));
+            assert_eq!(round_trip.into_parts(), parts);
+            kinds.push(kind);
+        }
+
+        let all_kinds = (0..=u8::MAX).filter_map(OperationKind::from_u8);
+        for kind in all_kinds.filter(|kind| *kind != OperationKind::Batch) {
+            assert!(kinds.contains(&kind), "{kind:?} has no round trip test input");
+        }
+    }
+
+    #[test]
+    fn kinds_round_trip_through_u8() {
+        for kind in (0..=u8::MAX).filter_map(OperationKind::from_u8) {
+            assert_eq!(OperationKind::from_u8(kind as u8), Some(kind));
+        }
+    }
+
+    #[test]
+    fn batch_is_not_a_single_input() {
+        assert!(OperationInput::from_parts(OperationKind::Batch, 0, Vec::new()).is_none());
+    }
+
+    #[test]
+    fn from_parts_rejects_malformed_payloads() {
+        assert!(OperationInput::from_parts(OperationKind::UpgradeAtEpoch, 0, vec![1; 7]).is_none());
+        assert!(OperationInput::from_parts(OperationKind::Shuffle, 0, vec![0; 39]).is_none());
+        assert!(OperationInput::from_parts(OperationKind::MerkleProof, 0, vec![0; 41]).is_none());
+        assert!(OperationInput::from_parts(OperationKind::StateQuery, 0, vec![0; 9]).is_none());
+        assert!(OperationInput::from_parts(OperationKind::EpochProcessing, 99, vec![]).is_none());
+        let bootstrap = OperationKind::LightClientBootstrap;
+        assert!(OperationInput::from_parts(bootstrap, 0, vec![0; 64]).is_none());
+        assert!(OperationInput::from_parts(bootstrap, 0, vec![0; 70]).is_none());
+    }
 }
@@ -1,13 +1,26 @@
-use std::fs;
+use std::path::Path;
 
+pub mod error;
 pub mod file;
+pub mod fork_choice;
+pub mod frame;
+pub mod fulu;
 pub mod input;
+pub mod light_client;
+pub mod merkle;
+pub mod merkle_proof;
+pub mod meta;
+pub mod output;
+pub mod rewards;
+pub mod shuffling;
 pub mod snappy;
+pub mod spec;
 pub mod ssz;
+pub mod ssz_static;
+pub mod state_query;
+pub mod witness;
 
 /// Loads an ELF file from the specified path.
-pub fn load_elf(path: &str) -> Vec<u8> {
-    fs::read(path).unwrap_or_else(|err| {
-        panic!("Failed to load ELF file from {}: {}", path, err);
-    })
+pub fn load_elf(path: &str) -> error::Result<Vec<u8>> {
+    file::read_file(Path::new(path))
 }
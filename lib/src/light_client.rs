@@ -0,0 +1,601 @@
+//! Light client sync protocol of the `light_client/sync` and `light_client/update_ranking` spec
+//! tests, for Electra headers.
+//!
+//! The guest initializes a [`LightClientStore`] from a bootstrap, processes updates against it
+//! and commits a [`LightClientSnapshot`] after every step. Validating an update checks the sync
+//! committee signature, the finality branch and the next sync committee branch.
+
+use std::path::Path;
+
+use alloy_primitives::{B256, FixedBytes};
+use ream_bls::{PubKey, traits::Verifiable};
+use ream_consensus::{
+    beacon_block_header::BeaconBlockHeader,
+    electra::execution_payload_header::ExecutionPayloadHeader, sync_aggregate::SyncAggregate,
+    sync_committee::SyncCommittee,
+};
+use serde::{Deserialize, Serialize};
+use ssz_derive::{Decode, Encode};
+use ssz_types::{
+    FixedVector,
+    typenum::{U4, U6, U7},
+};
+use tree_hash::TreeHash;
+use tree_hash_derive::TreeHash;
+
+use crate::error::{Error, Result};
+use crate::file::read_file;
+use crate::spec::{
+    MAINNET_FORK_VERSIONS, SLOTS_PER_EPOCH, compute_domain, compute_fork_data_root,
+    compute_signing_root,
+};
+use crate::witness::is_valid_merkle_branch;
+
+pub const EPOCHS_PER_SYNC_COMMITTEE_PERIOD: u64 = 256;
+pub const MIN_SYNC_COMMITTEE_PARTICIPANTS: usize = 1;
+pub const UPDATE_TIMEOUT: u64 = SLOTS_PER_EPOCH * EPOCHS_PER_SYNC_COMMITTEE_PERIOD;
+pub const DOMAIN_SYNC_COMMITTEE: [u8; 4] = [7, 0, 0, 0];
+
+/// `EXECUTION_PAYLOAD_GINDEX` in `BeaconBlockBody`.
+pub const EXECUTION_PAYLOAD_GINDEX: u64 = 25;
+pub const FINALIZED_ROOT_GINDEX_ELECTRA: u64 = 169;
+pub const CURRENT_SYNC_COMMITTEE_GINDEX_ELECTRA: u64 = 86;
+pub const NEXT_SYNC_COMMITTEE_GINDEX_ELECTRA: u64 = 87;
+
+/// Deneb `LightClientHeader`, unchanged in Electra.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Encode, Decode, TreeHash)]
+pub struct LightClientHeader {
+    pub beacon: BeaconBlockHeader,
+    pub execution: ExecutionPayloadHeader,
+    pub execution_branch: FixedVector<B256, U4>,
+}
+
+/// Electra `LightClientBootstrap`.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, TreeHash)]
+pub struct LightClientBootstrap {
+    pub header: LightClientHeader,
+    pub current_sync_committee: SyncCommittee,
+    pub current_sync_committee_branch: FixedVector<B256, U6>,
+}
+
+/// Electra `LightClientUpdate`.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, TreeHash)]
+pub struct LightClientUpdate {
+    pub attested_header: LightClientHeader,
+    pub next_sync_committee: SyncCommittee,
+    pub next_sync_committee_branch: FixedVector<B256, U6>,
+    pub finalized_header: LightClientHeader,
+    pub finality_branch: FixedVector<B256, U7>,
+    pub sync_aggregate: SyncAggregate,
+    pub signature_slot: u64,
+}
+
+/// Fork versions and the epochs they activate at, in activation order.
+pub type ForkSchedule = Vec<(u64, [u8; 4])>;
+
+/// Fork versions and epochs of a sync case's `config.yaml`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+struct ForkConfig {
+    genesis_fork_version: String,
+    altair_fork_version: String,
+    altair_fork_epoch: u64,
+    bellatrix_fork_version: String,
+    bellatrix_fork_epoch: u64,
+    capella_fork_version: String,
+    capella_fork_epoch: u64,
+    deneb_fork_version: String,
+    deneb_fork_epoch: u64,
+    electra_fork_version: String,
+    electra_fork_epoch: u64,
+}
+
+/// `LightClientStore`, with an unknown next sync committee as `None`.
+#[derive(Debug, Clone)]
+pub struct LightClientStore {
+    pub finalized_header: LightClientHeader,
+    pub current_sync_committee: SyncCommittee,
+    pub next_sync_committee: Option<SyncCommittee>,
+    pub best_valid_update: Option<LightClientUpdate>,
+    pub optimistic_header: LightClientHeader,
+    pub previous_max_active_participants: u64,
+    pub current_max_active_participants: u64,
+    pub genesis_validators_root: B256,
+    /// Forks whose versions sign the processed updates, see [`Self::compute_fork_version`].
+    pub fork_schedule: ForkSchedule,
+}
+
+/// Store headers committed by the guest after a `process_update` or `force_update` step.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LightClientSnapshot {
+    pub finalized_slot: u64,
+    pub finalized_root: B256,
+    pub optimistic_slot: u64,
+    pub optimistic_root: B256,
+}
+
+pub fn compute_sync_committee_period_at_slot(slot: u64) -> u64 {
+    slot / SLOTS_PER_EPOCH / EPOCHS_PER_SYNC_COMMITTEE_PERIOD
+}
+
+/// `is_valid_normalized_merkle_branch`: branches longer than the depth of `gindex` must be padded
+/// with zero hashes.
+pub fn is_valid_normalized_merkle_branch(
+    leaf: B256,
+    branch: &[B256],
+    gindex: u64,
+    root: B256,
+) -> bool {
+    let depth = gindex.ilog2() as usize;
+    let index = gindex % (1 << depth);
+    let Some(extra) = branch.len().checked_sub(depth) else {
+        return false;
+    };
+
+    branch[..extra].iter().all(B256::is_zero)
+        && is_valid_merkle_branch(leaf, &branch[extra..], depth, index, root)
+}
+
+/// `is_valid_light_client_header` for headers from Capella on, whose execution header is proven
+/// against the block body.
+pub fn is_valid_light_client_header(header: &LightClientHeader) -> bool {
+    is_valid_normalized_merkle_branch(
+        header.execution.tree_hash_root(),
+        &header.execution_branch,
+        EXECUTION_PAYLOAD_GINDEX,
+        header.beacon.body_root,
+    )
+}
+
+/// Fork version of the mainnet config whose fork digest for `genesis_validators_root` is
+/// `fork_digest`.
+pub fn fork_version_of_digest(
+    fork_digest: FixedBytes<4>,
+    genesis_validators_root: B256,
+) -> Option<[u8; 4]> {
+    MAINNET_FORK_VERSIONS.into_iter().find(|fork_version| {
+        compute_fork_data_root(*fork_version, genesis_validators_root)[..4] == fork_digest[..]
+    })
+}
+
+fn num_active_participants(sync_aggregate: &SyncAggregate) -> usize {
+    sync_aggregate.sync_committee_bits.num_set_bits()
+}
+
+pub fn is_sync_committee_update(update: &LightClientUpdate) -> bool {
+    !update.next_sync_committee_branch.iter().all(B256::is_zero)
+}
+
+pub fn is_finality_update(update: &LightClientUpdate) -> bool {
+    !update.finality_branch.iter().all(B256::is_zero)
+}
+
+/// `is_better_update`: whether `new_update` should replace `old_update` as the best valid update.
+pub fn is_better_update(new_update: &LightClientUpdate, old_update: &LightClientUpdate) -> bool {
+    // Compare supermajority (> 2/3) sync committee participation
+    let max_active_participants = new_update.sync_aggregate.sync_committee_bits.len();
+    let new_num_active_participants = num_active_participants(&new_update.sync_aggregate);
+    let old_num_active_participants = num_active_participants(&old_update.sync_aggregate);
+    let new_has_supermajority = new_num_active_participants * 3 >= max_active_participants * 2;
+    let old_has_supermajority = old_num_active_participants * 3 >= max_active_participants * 2;
+    if new_has_supermajority != old_has_supermajority {
+        return new_has_supermajority;
+    }
+    if !new_has_supermajority && new_num_active_participants != old_num_active_participants {
+        return new_num_active_participants > old_num_active_participants;
+    }
+
+    // Compare presence of relevant sync committee
+    let has_relevant_sync_committee = |update: &LightClientUpdate| {
+        is_sync_committee_update(update)
+            && compute_sync_committee_period_at_slot(update.attested_header.beacon.slot)
+                == compute_sync_committee_period_at_slot(update.signature_slot)
+    };
+    let new_has_relevant_sync_committee = has_relevant_sync_committee(new_update);
+    if new_has_relevant_sync_committee != has_relevant_sync_committee(old_update) {
+        return new_has_relevant_sync_committee;
+    }
+
+    // Compare indication of any finality
+    let new_has_finality = is_finality_update(new_update);
+    if new_has_finality != is_finality_update(old_update) {
+        return new_has_finality;
+    }
+
+    // Compare sync committee finality
+    if new_has_finality {
+        let has_sync_committee_finality = |update: &LightClientUpdate| {
+            compute_sync_committee_period_at_slot(update.finalized_header.beacon.slot)
+                == compute_sync_committee_period_at_slot(update.attested_header.beacon.slot)
+        };
+        let new_has_sync_committee_finality = has_sync_committee_finality(new_update);
+        if new_has_sync_committee_finality != has_sync_committee_finality(old_update) {
+            return new_has_sync_committee_finality;
+        }
+    }
+
+    // Tiebreaker 1: Sync committee participation beyond supermajority
+    if new_num_active_participants != old_num_active_participants {
+        return new_num_active_participants > old_num_active_participants;
+    }
+
+    // Tiebreaker 2: Prefer older data (fewer changes to best)
+    if new_update.attested_header.beacon.slot != old_update.attested_header.beacon.slot {
+        return new_update.attested_header.beacon.slot < old_update.attested_header.beacon.slot;
+    }
+    new_update.signature_slot < old_update.signature_slot
+}
+
+impl LightClientStore {
+    /// `initialize_light_client_store`, or `None` if the bootstrap does not match
+    /// `trusted_block_root`.
+    pub fn initialize(
+        trusted_block_root: B256,
+        bootstrap: LightClientBootstrap,
+        genesis_validators_root: B256,
+        fork_schedule: ForkSchedule,
+    ) -> Option<Self> {
+        let valid = is_valid_light_client_header(&bootstrap.header)
+            && bootstrap.header.beacon.tree_hash_root() == trusted_block_root
+            && is_valid_normalized_merkle_branch(
+                bootstrap.current_sync_committee.tree_hash_root(),
+                &bootstrap.current_sync_committee_branch,
+                CURRENT_SYNC_COMMITTEE_GINDEX_ELECTRA,
+                bootstrap.header.beacon.state_root,
+            );
+        if !valid {
+            return None;
+        }
+
+        Some(Self {
+            finalized_header: bootstrap.header.clone(),
+            current_sync_committee: bootstrap.current_sync_committee,
+            next_sync_committee: None,
+            best_valid_update: None,
+            optimistic_header: bootstrap.header,
+            previous_max_active_participants: 0,
+            current_max_active_participants: 0,
+            genesis_validators_root,
+            fork_schedule,
+        })
+    }
+
+    pub fn snapshot(&self) -> LightClientSnapshot {
+        LightClientSnapshot {
+            finalized_slot: self.finalized_header.beacon.slot,
+            finalized_root: self.finalized_header.beacon.tree_hash_root(),
+            optimistic_slot: self.optimistic_header.beacon.slot,
+            optimistic_root: self.optimistic_header.beacon.tree_hash_root(),
+        }
+    }
+
+    fn safety_threshold(&self) -> u64 {
+        (self.previous_max_active_participants + self.current_max_active_participants) / 2
+    }
+
+    /// `validate_light_client_update`.
+    pub fn validate_update(&self, update: &LightClientUpdate, current_slot: u64) -> bool {
+        // Verify sync committee has sufficient participants
+        if num_active_participants(&update.sync_aggregate) < MIN_SYNC_COMMITTEE_PARTICIPANTS {
+            return false;
+        }
+
+        // Verify update does not skip a sync committee period
+        if !is_valid_light_client_header(&update.attested_header) {
+            return false;
+        }
+        let update_attested_slot = update.attested_header.beacon.slot;
+        let update_finalized_slot = update.finalized_header.beacon.slot;
+        if !(current_slot >= update.signature_slot
+            && update.signature_slot > update_attested_slot
+            && update_attested_slot >= update_finalized_slot)
+        {
+            return false;
+        }
+        let store_period = compute_sync_committee_period_at_slot(self.finalized_header.beacon.slot);
+        let update_signature_period = compute_sync_committee_period_at_slot(update.signature_slot);
+        let signature_period_known = match self.next_sync_committee {
+            Some(_) => {
+                update_signature_period == store_period
+                    || update_signature_period == store_period + 1
+            }
+            None => update_signature_period == store_period,
+        };
+        if !signature_period_known {
+            return false;
+        }
+
+        // Verify update is relevant
+        let update_attested_period = compute_sync_committee_period_at_slot(update_attested_slot);
+        let update_has_next_sync_committee = self.next_sync_committee.is_none()
+            && is_sync_committee_update(update)
+            && update_attested_period == store_period;
+        if !(update_attested_slot > self.finalized_header.beacon.slot
+            || update_has_next_sync_committee)
+        {
+            return false;
+        }
+
+        // Verify that the `finality_branch`, if present, confirms `finalized_header` to match the
+        // finalized checkpoint root saved in the state of `attested_header`
+        if !is_finality_update(update) {
+            if update.finalized_header != LightClientHeader::default() {
+                return false;
+            }
+        } else {
+            let finalized_root = if update_finalized_slot == 0 {
+                if update.finalized_header != LightClientHeader::default() {
+                    return false;
+                }
+                B256::ZERO
+            } else {
+                if !is_valid_light_client_header(&update.finalized_header) {
+                    return false;
+                }
+                update.finalized_header.beacon.tree_hash_root()
+            };
+            if !is_valid_normalized_merkle_branch(
+                finalized_root,
+                &update.finality_branch,
+                FINALIZED_ROOT_GINDEX_ELECTRA,
+                update.attested_header.beacon.state_root,
+            ) {
+                return false;
+            }
+        }
+
+        // Verify that the `next_sync_committee`, if present, actually is the next sync committee
+        // saved in the state of the `attested_header`
+        if !is_sync_committee_update(update) {
+            if update.next_sync_committee != SyncCommittee::default() {
+                return false;
+            }
+        } else {
+            if update_attested_period == store_period {
+                if let Some(next_sync_committee) = &self.next_sync_committee {
+                    if update.next_sync_committee != *next_sync_committee {
+                        return false;
+                    }
+                }
+            }
+            if !is_valid_normalized_merkle_branch(
+                update.next_sync_committee.tree_hash_root(),
+                &update.next_sync_committee_branch,
+                NEXT_SYNC_COMMITTEE_GINDEX_ELECTRA,
+                update.attested_header.beacon.state_root,
+            ) {
+                return false;
+            }
+        }
+
+        // Verify sync committee aggregate signature
+        let sync_committee = if update_signature_period == store_period {
+            &self.current_sync_committee
+        } else {
+            match &self.next_sync_committee {
+                Some(next_sync_committee) => next_sync_committee,
+                None => return false,
+            }
+        };
+        let participant_pubkeys: Vec<&PubKey> = sync_committee
+            .pubkeys
+            .iter()
+            .zip(update.sync_aggregate.sync_committee_bits.iter())
+            .filter_map(|(pubkey, bit)| bit.then_some(pubkey))
+            .collect();
+        let fork_version_slot = update.signature_slot.max(1) - 1;
+        let fork_version = self.compute_fork_version(fork_version_slot / SLOTS_PER_EPOCH);
+        let domain = compute_domain(
+            DOMAIN_SYNC_COMMITTEE,
+            fork_version,
+            self.genesis_validators_root,
+        );
+        let signing_root =
+            compute_signing_root(update.attested_header.beacon.tree_hash_root(), domain);
+
+        update
+            .sync_aggregate
+            .sync_committee_signature
+            .fast_aggregate_verify(&participant_pubkeys, signing_root.as_slice())
+            .unwrap_or(false)
+    }
+
+    /// `compute_fork_version`: version of the last fork of the schedule active at `epoch`.
+    pub fn compute_fork_version(&self, epoch: u64) -> [u8; 4] {
+        self.fork_schedule
+            .iter()
+            .rev()
+            .find(|(fork_epoch, _)| *fork_epoch <= epoch)
+            .or(self.fork_schedule.first())
+            .map_or([0; 4], |(_, fork_version)| *fork_version)
+    }
+
+    /// `apply_light_client_update`, returning `false` without applying the update if it fails
+    /// the spec's assertion.
+    fn apply_update(&mut self, update: &LightClientUpdate) -> bool {
+        let store_period = compute_sync_committee_period_at_slot(self.finalized_header.beacon.slot);
+        let update_finalized_period =
+            compute_sync_committee_period_at_slot(update.finalized_header.beacon.slot);
+        if self.next_sync_committee.is_none() {
+            if update_finalized_period != store_period {
+                return false;
+            }
+            self.next_sync_committee = Some(update.next_sync_committee.clone());
+        } else if update_finalized_period == store_period + 1 {
+            if let Some(next_sync_committee) = self.next_sync_committee.take() {
+                self.current_sync_committee = next_sync_committee;
+            }
+            self.next_sync_committee = Some(update.next_sync_committee.clone());
+            self.previous_max_active_participants = self.current_max_active_participants;
+            self.current_max_active_participants = 0;
+        }
+        if update.finalized_header.beacon.slot > self.finalized_header.beacon.slot {
+            self.finalized_header = update.finalized_header.clone();
+            if self.finalized_header.beacon.slot > self.optimistic_header.beacon.slot {
+                self.optimistic_header = self.finalized_header.clone();
+            }
+        }
+
+        true
+    }
+
+    /// `process_light_client_store_force_update`, returning `false` if the best valid update
+    /// cannot be applied.
+    pub fn force_update(&mut self, current_slot: u64) -> bool {
+        if current_slot <= self.finalized_header.beacon.slot + UPDATE_TIMEOUT {
+            return true;
+        }
+        // Forced best update when the update timeout has elapsed. Because the apply logic waits
+        // for `finalized_header.beacon.slot` to indicate sync committee finality, the
+        // `attested_header` may be treated as `finalized_header` in extended periods of
+        // non-finality to guarantee progression into later sync committee periods according to
+        // `is_better_update`.
+        if let Some(mut best_valid_update) = self.best_valid_update.take() {
+            if best_valid_update.finalized_header.beacon.slot <= self.finalized_header.beacon.slot {
+                best_valid_update.finalized_header = best_valid_update.attested_header.clone();
+            }
+            return self.apply_update(&best_valid_update);
+        }
+
+        true
+    }
+
+    /// `process_light_client_update`, returning `false` if the update is invalid.
+    pub fn process_update(&mut self, update: LightClientUpdate, current_slot: u64) -> bool {
+        if !self.validate_update(&update, current_slot) {
+            return false;
+        }
+        let num_active_participants = num_active_participants(&update.sync_aggregate) as u64;
+
+        // Update the best update in case we have to force-update to it if the timeout elapses
+        let is_best = self
+            .best_valid_update
+            .as_ref()
+            .is_none_or(|best_valid_update| is_better_update(&update, best_valid_update));
+        if is_best {
+            self.best_valid_update = Some(update.clone());
+        }
+
+        // Track the maximum number of active participants in the committee signatures
+        self.current_max_active_participants =
+            self.current_max_active_participants.max(num_active_participants);
+
+        // Update the optimistic header
+        if num_active_participants > self.safety_threshold()
+            && update.attested_header.beacon.slot > self.optimistic_header.beacon.slot
+        {
+            self.optimistic_header = update.attested_header.clone();
+        }
+
+        // Update finalized header
+        let update_has_finalized_next_sync_committee = self.next_sync_committee.is_none()
+            && is_sync_committee_update(&update)
+            && is_finality_update(&update)
+            && compute_sync_committee_period_at_slot(update.finalized_header.beacon.slot)
+                == compute_sync_committee_period_at_slot(update.attested_header.beacon.slot);
+        let max_active_participants = update.sync_aggregate.sync_committee_bits.len() as u64;
+        if num_active_participants * 3 >= max_active_participants * 2
+            && (update.finalized_header.beacon.slot > self.finalized_header.beacon.slot
+                || update_has_finalized_next_sync_committee)
+        {
+            // Normal update through 2/3 threshold
+            if !self.apply_update(&update) {
+                return false;
+            }
+            self.best_valid_update = None;
+        }
+
+        true
+    }
+}
+
+/// One entry of a `light_client/sync` case's `steps.yaml`. Other steps, such as
+/// `upgrade_store`, fail to load.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStep {
+    ProcessUpdate {
+        /// Name of the `<update>.ssz_snappy` file of the case.
+        update: String,
+        current_slot: u64,
+        checks: SyncChecks,
+    },
+    ForceUpdate {
+        current_slot: u64,
+        checks: SyncChecks,
+    },
+}
+
+/// Expected header of a sync step.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderCheck {
+    pub slot: u64,
+    pub beacon_root: B256,
+}
+
+/// Expected store headers after a sync step.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct SyncChecks {
+    pub finalized_header: HeaderCheck,
+    pub optimistic_header: HeaderCheck,
+}
+
+impl SyncStep {
+    pub fn checks(&self) -> &SyncChecks {
+        match self {
+            SyncStep::ProcessUpdate { checks, .. } | SyncStep::ForceUpdate { checks, .. } => checks,
+        }
+    }
+}
+
+impl SyncChecks {
+    pub fn matches(&self, snapshot: &LightClientSnapshot) -> bool {
+        self.finalized_header.slot == snapshot.finalized_slot
+            && self.finalized_header.beacon_root == snapshot.finalized_root
+            && self.optimistic_header.slot == snapshot.optimistic_slot
+            && self.optimistic_header.beacon_root == snapshot.optimistic_root
+    }
+}
+
+/// Loads the fork schedule of a `light_client/sync` case from `config.yaml` in `case_dir`, or
+/// `None` if the case has none.
+pub fn load_fork_schedule(case_dir: &Path) -> Result<Option<ForkSchedule>> {
+    let path = case_dir.join("config.yaml");
+    if !path.exists() {
+        return Ok(None);
+    }
+    let bytes = read_file(&path)?;
+    let config: ForkConfig = serde_yaml::from_slice(&bytes).map_err(|source| Error::Yaml {
+        path: path.clone(),
+        source,
+    })?;
+
+    [
+        (0, config.genesis_fork_version),
+        (config.altair_fork_epoch, config.altair_fork_version),
+        (config.bellatrix_fork_epoch, config.bellatrix_fork_version),
+        (config.capella_fork_epoch, config.capella_fork_version),
+        (config.deneb_fork_epoch, config.deneb_fork_version),
+        (config.electra_fork_epoch, config.electra_fork_version),
+    ]
+    .into_iter()
+    .map(|(epoch, fork_version)| {
+        let parsed = fork_version.parse::<FixedBytes<4>>().map_err(|_| {
+            Error::InvalidForkVersion {
+                path: path.clone(),
+                fork_version: fork_version.clone(),
+            }
+        })?;
+        Ok((epoch, parsed.0))
+    })
+    .collect::<Result<_>>()
+    .map(Some)
+}
+
+/// Loads `steps.yaml` from a `light_client/sync` case in `case_dir`.
+pub fn load_sync_steps(case_dir: &Path) -> Result<Vec<SyncStep>> {
+    let path = case_dir.join("steps.yaml");
+    let bytes = read_file(&path)?;
+
+    serde_yaml::from_slice(&bytes).map_err(|source| Error::Yaml { path, source })
+}
@@ -0,0 +1,710 @@
+//! Incremental merkleization of the Electra `BeaconState` from cached roots.
+//!
+//! The state root is the merkle root of the state's field roots. The host sends a
+//! [`StateRootCache`] of the pre-state: the root of every field, and every node of the subtrees
+//! of the large lists in [`SUBTREE_FIELDS`]. The guest then re-hashes only what the operation
+//! changed: a changed list of [`SUBTREE_FIELDS`] only has the paths from its changed leaves to its
+//! root re-hashed, and any other changed field is re-hashed whole. The pre-value of a changed leaf
+//! or field is checked against its cached root before the cache entry is replaced.
+//!
+//! Unchanged fields and leaves are not re-hashed, so the guest trusts the host that their cached
+//! roots match the pre-state bytes. The mode measures hashing cost; it is not a soundness
+//! boundary.
+
+use ethereum_hashing::hash32_concat;
+use ream_consensus::electra::beacon_state::BeaconState;
+use ssz::DecodeError;
+use tree_hash::{Hash256, TreeHash, merkle_root, mix_in_length};
+
+use crate::ssz::LazyBeaconState;
+
+macro_rules! for_each_beacon_state_field {
+    ($callback:ident!($($args:tt)*)) => {
+        $callback!(
+            $($args)*;
+            genesis_time,
+            genesis_validators_root,
+            slot,
+            fork,
+            latest_block_header,
+            block_roots,
+            state_roots,
+            historical_roots,
+            eth1_data,
+            eth1_data_votes,
+            eth1_deposit_index,
+            validators,
+            balances,
+            randao_mixes,
+            slashings,
+            previous_epoch_participation,
+            current_epoch_participation,
+            justification_bits,
+            previous_justified_checkpoint,
+            current_justified_checkpoint,
+            finalized_checkpoint,
+            inactivity_scores,
+            current_sync_committee,
+            next_sync_committee,
+            latest_execution_payload_header,
+            next_withdrawal_index,
+            next_withdrawal_validator_index,
+            historical_summaries,
+            deposit_requests_start_index,
+            deposit_balance_to_consume,
+            exit_balance_to_consume,
+            earliest_exit_epoch,
+            consolidation_balance_to_consume,
+            earliest_consolidation_epoch,
+            pending_deposits,
+            pending_partial_withdrawals,
+            pending_consolidations
+        )
+    };
+}
+
+pub(crate) use for_each_beacon_state_field;
+
+macro_rules! field_roots {
+    ($state:ident; $($field:ident),*) => {
+        vec![$($state.$field.tree_hash_root()),*]
+    };
+}
+
+macro_rules! incremental_field_roots {
+    ($pre_state:ident, $post_state:ident, $cached:ident; $($field:ident),*) => {{
+        let mut index = 0;
+        let mut roots = Vec::with_capacity(BEACON_STATE_FIELD_COUNT);
+        $(
+            let cached_root = $cached[index];
+            // Lists with a cached subtree are updated leaf by leaf afterwards
+            if SUBTREE_FIELDS.contains(&index) || $pre_state.$field == $post_state.$field {
+                roots.push(cached_root);
+            } else {
+                if $pre_state.$field.tree_hash_root() != cached_root {
+                    return None;
+                }
+                roots.push($post_state.$field.tree_hash_root());
+            }
+            index += 1;
+        )*
+        let _ = index;
+        roots
+    }};
+}
+
+/// Number of fields of the Electra `BeaconState`.
+pub const BEACON_STATE_FIELD_COUNT: usize = 37;
+
+/// Positions of `BeaconState` fields, in declaration order.
+pub mod field_index {
+    pub const GENESIS_VALIDATORS_ROOT: usize = 1;
+    pub const SLOT: usize = 2;
+    pub const LATEST_BLOCK_HEADER: usize = 4;
+    pub const BLOCK_ROOTS: usize = 5;
+    pub const STATE_ROOTS: usize = 6;
+    pub const VALIDATORS: usize = 11;
+    pub const BALANCES: usize = 12;
+    pub const RANDAO_MIXES: usize = 13;
+    pub const PREVIOUS_EPOCH_PARTICIPATION: usize = 15;
+    pub const CURRENT_EPOCH_PARTICIPATION: usize = 16;
+    pub const INACTIVITY_SCORES: usize = 21;
+}
+
+/// Lists whose subtrees a [`StateRootCache`] holds, in the order of its subtrees.
+pub const SUBTREE_FIELDS: [usize; 5] = [
+    field_index::VALIDATORS,
+    field_index::BALANCES,
+    field_index::PREVIOUS_EPOCH_PARTICIPATION,
+    field_index::CURRENT_EPOCH_PARTICIPATION,
+    field_index::INACTIVITY_SCORES,
+];
+
+/// How the lazy path hashes a `BeaconState` field.
+#[derive(Clone, Copy)]
+enum FieldHashing {
+    /// A basic value or a root, whose SSZ bytes padded to 32 bytes are its root.
+    Basic,
+    /// A vector of basic values or roots, whose SSZ bytes are its packed leaves.
+    PackedVector,
+    /// A list of `item_len`-byte basic values or roots, packed into a tree of `depth`.
+    PackedList { item_len: usize, depth: usize },
+    /// A container or a list of containers, decoded before it is hashed.
+    Decoded,
+}
+
+use FieldHashing::{Basic, Decoded, PackedList, PackedVector};
+
+/// Depth of `List[Root, HISTORICAL_ROOTS_LIMIT]`.
+const HISTORICAL_ROOTS_DEPTH: usize = 24;
+/// Depth of `List[Validator, VALIDATOR_REGISTRY_LIMIT]`.
+const VALIDATORS_DEPTH: usize = 40;
+/// Depth of `List[uint64, VALIDATOR_REGISTRY_LIMIT]`, four per leaf.
+const U64_LIST_DEPTH: usize = 38;
+/// Depth of `List[ParticipationFlags, VALIDATOR_REGISTRY_LIMIT]`, 32 per leaf.
+const PARTICIPATION_LIST_DEPTH: usize = 35;
+
+/// Hashing of every `BeaconState` field, in declaration order.
+const FIELD_HASHING: [FieldHashing; BEACON_STATE_FIELD_COUNT] = [
+    Basic,
+    Basic,
+    Basic,
+    Decoded,
+    Decoded,
+    PackedVector,
+    PackedVector,
+    PackedList {
+        item_len: 32,
+        depth: HISTORICAL_ROOTS_DEPTH,
+    },
+    Decoded,
+    Decoded,
+    Basic,
+    Decoded,
+    PackedList {
+        item_len: 8,
+        depth: U64_LIST_DEPTH,
+    },
+    PackedVector,
+    PackedVector,
+    PackedList {
+        item_len: 1,
+        depth: PARTICIPATION_LIST_DEPTH,
+    },
+    PackedList {
+        item_len: 1,
+        depth: PARTICIPATION_LIST_DEPTH,
+    },
+    Basic,
+    Decoded,
+    Decoded,
+    Decoded,
+    PackedList {
+        item_len: 8,
+        depth: U64_LIST_DEPTH,
+    },
+    Decoded,
+    Decoded,
+    Decoded,
+    Basic,
+    Basic,
+    Decoded,
+    Basic,
+    Basic,
+    Basic,
+    Basic,
+    Basic,
+    Basic,
+    Decoded,
+    Decoded,
+    Decoded,
+];
+
+/// Computes the root of every field of `state`, in declaration order.
+pub fn field_roots(state: &BeaconState) -> Vec<Hash256> {
+    for_each_beacon_state_field!(field_roots!(state))
+}
+
+/// Merkleizes field roots into the state root.
+pub fn root_from_field_roots(field_roots: &[Hash256]) -> Hash256 {
+    let bytes: Vec<u8> = field_roots.iter().flat_map(|root| root.0).collect();
+    merkle_root(&bytes, field_roots.len())
+}
+
+fn hash_pair(left: Hash256, right: Hash256) -> Hash256 {
+    Hash256::from_slice(&hash32_concat(left.as_slice(), right.as_slice()))
+}
+
+/// Pairs the root of a subtree of `subtree_depth` with zero subtrees up to `depth`, whose leaf
+/// counts overflow the guest's `usize`.
+fn pad_to_depth(subtree_root: Hash256, subtree_depth: usize, depth: usize) -> Hash256 {
+    let mut root = subtree_root;
+    let mut zero_subtree = Hash256::ZERO;
+    for height in 0..depth {
+        if height >= subtree_depth {
+            root = hash_pair(root, zero_subtree);
+        }
+        zero_subtree = hash_pair(zero_subtree, zero_subtree);
+    }
+    root
+}
+
+/// Leaves of a list of basic values, packed 32 bytes per leaf.
+fn packed_leaves(bytes: &[u8]) -> Vec<Hash256> {
+    bytes
+        .chunks(32)
+        .map(|chunk| {
+            let mut leaf = Hash256::ZERO;
+            leaf[..chunk.len()].copy_from_slice(chunk);
+            leaf
+        })
+        .collect()
+}
+
+fn u64_bytes(values: &[u64]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+/// Every layer of the smallest power-of-two subtree holding the leaves of a list, from the leaves
+/// up to the subtree root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtreeCache {
+    layers: Vec<Vec<Hash256>>,
+}
+
+impl SubtreeCache {
+    pub fn from_leaves(mut leaves: Vec<Hash256>) -> Self {
+        leaves.resize(leaves.len().next_power_of_two(), Hash256::ZERO);
+        let mut layers = vec![leaves];
+        while let Some(layer) = layers.last().filter(|layer| layer.len() > 1) {
+            let parents = layer
+                .chunks_exact(2)
+                .map(|pair| hash_pair(pair[0], pair[1]))
+                .collect();
+            layers.push(parents);
+        }
+
+        Self { layers }
+    }
+
+    /// Root of a list of `len` items in a tree of `depth` whose first leaves are this subtree's.
+    pub fn list_root(&self, depth: usize, len: usize) -> Hash256 {
+        let subtree_root = self.layers[self.layers.len() - 1][0];
+        mix_in_length(
+            &pad_to_depth(subtree_root, self.layers.len() - 1, depth),
+            len,
+        )
+    }
+
+    /// Returns the root of the list `post` of `post_len` items in a tree of `depth`, by replacing
+    /// the leaves that differ from `pre` and re-hashing the paths from those leaves to the subtree
+    /// root. `pre` holds the `pre_len` items whose list root is `cached_root`.
+    ///
+    /// Returns `None` if the subtree does not merkleize to `cached_root` or if a changed `pre`
+    /// leaf does not match its cached leaf.
+    fn updated_list_root<T: PartialEq>(
+        &mut self,
+        cached_root: Hash256,
+        depth: usize,
+        (pre, pre_len): (&[T], usize),
+        (post, post_len): (&[T], usize),
+        leaf: impl Fn(&T) -> Hash256,
+    ) -> Option<Hash256> {
+        if self.list_root(depth, pre_len) != cached_root {
+            return None;
+        }
+
+        // A list that outgrows the subtree is rebuilt from its items
+        if post.len() > self.layers[0].len() {
+            let pre_matches = pre
+                .iter()
+                .enumerate()
+                .all(|(index, item)| self.layers[0].get(index) == Some(&leaf(item)));
+            *self = Self::from_leaves(post.iter().map(&leaf).collect());
+            return pre_matches.then(|| self.list_root(depth, post_len));
+        }
+
+        let mut dirty = Vec::new();
+        for index in 0..pre.len().max(post.len()) {
+            let (pre_item, post_item) = (pre.get(index), post.get(index));
+            if pre_item == post_item {
+                continue;
+            }
+            if pre_item.is_some_and(|item| leaf(item) != self.layers[0][index]) {
+                return None;
+            }
+            self.layers[0][index] = post_item.map_or(Hash256::ZERO, &leaf);
+            dirty.push(index);
+        }
+
+        for height in 1..self.layers.len() {
+            for index in dirty.iter_mut() {
+                *index /= 2;
+            }
+            dirty.dedup();
+            for &index in &dirty {
+                let children = &self.layers[height - 1];
+                let parent = hash_pair(children[2 * index], children[2 * index + 1]);
+                self.layers[height][index] = parent;
+            }
+        }
+        Some(self.list_root(depth, post_len))
+    }
+
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&(self.layers[0].len() as u32).to_le_bytes());
+        for node in self.layers.iter().flatten() {
+            bytes.extend_from_slice(node.as_slice());
+        }
+    }
+
+    /// Decodes a subtree written by [`SubtreeCache::encode`] from the front of `bytes`, returning
+    /// it with the remaining bytes.
+    fn decode(bytes: &[u8]) -> Option<(Self, &[u8])> {
+        let (capacity, mut rest) = bytes.split_first_chunk::<4>()?;
+        let capacity = u32::from_le_bytes(*capacity) as usize;
+        if !capacity.is_power_of_two() {
+            return None;
+        }
+
+        let mut layers = Vec::new();
+        let mut layer_len = capacity;
+        while layer_len > 0 {
+            let (layer, remaining) = rest.split_at_checked(layer_len * 32)?;
+            layers.push(layer.chunks_exact(32).map(Hash256::from_slice).collect());
+            rest = remaining;
+            layer_len /= 2;
+        }
+        Some((Self { layers }, rest))
+    }
+}
+
+/// Cached roots of a pre-state for incremental merkleization: the root of every field, and the
+/// subtrees of the lists in [`SUBTREE_FIELDS`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateRootCache {
+    field_roots: Vec<Hash256>,
+    subtrees: Vec<SubtreeCache>,
+}
+
+impl StateRootCache {
+    pub fn from_state(state: &BeaconState) -> Self {
+        let validator_roots = state
+            .validators
+            .iter()
+            .map(TreeHash::tree_hash_root)
+            .collect();
+
+        Self {
+            field_roots: field_roots(state),
+            subtrees: vec![
+                SubtreeCache::from_leaves(validator_roots),
+                SubtreeCache::from_leaves(packed_leaves(&u64_bytes(&state.balances))),
+                SubtreeCache::from_leaves(packed_leaves(&state.previous_epoch_participation)),
+                SubtreeCache::from_leaves(packed_leaves(&state.current_epoch_participation)),
+                SubtreeCache::from_leaves(packed_leaves(&u64_bytes(&state.inactivity_scores))),
+            ],
+        }
+    }
+
+    /// Root that the cached field roots merkleize to. Only the host checks it against the
+    /// pre-state; the guest does not tie it to the pre-state bytes it decoded.
+    pub fn root(&self) -> Hash256 {
+        root_from_field_roots(&self.field_roots)
+    }
+
+    /// Computes the root of `post_state` by re-hashing only what differs from `pre_state`.
+    ///
+    /// Returns `None` if a cached root of a changed field or leaf does not match `pre_state`, or
+    /// if the cached subtree of a changed list does not match its cached field root.
+    pub fn incremental_state_root(
+        mut self,
+        pre_state: &BeaconState,
+        post_state: &BeaconState,
+    ) -> Option<Hash256> {
+        let cached = &self.field_roots;
+        let mut roots =
+            for_each_beacon_state_field!(incremental_field_roots!(pre_state, post_state, cached));
+
+        let [
+            validators,
+            balances,
+            previous_participation,
+            current_participation,
+            inactivity,
+        ] = &mut self.subtrees[..]
+        else {
+            return None;
+        };
+        if pre_state.validators != post_state.validators {
+            roots[field_index::VALIDATORS] = validators.updated_list_root(
+                roots[field_index::VALIDATORS],
+                VALIDATORS_DEPTH,
+                (&pre_state.validators[..], pre_state.validators.len()),
+                (&post_state.validators[..], post_state.validators.len()),
+                |validator| validator.tree_hash_root(),
+            )?;
+        }
+        if pre_state.balances != post_state.balances {
+            roots[field_index::BALANCES] = balances.updated_list_root(
+                roots[field_index::BALANCES],
+                U64_LIST_DEPTH,
+                (
+                    &packed_leaves(&u64_bytes(&pre_state.balances)),
+                    pre_state.balances.len(),
+                ),
+                (
+                    &packed_leaves(&u64_bytes(&post_state.balances)),
+                    post_state.balances.len(),
+                ),
+                |leaf| *leaf,
+            )?;
+        }
+        if pre_state.previous_epoch_participation != post_state.previous_epoch_participation {
+            roots[field_index::PREVIOUS_EPOCH_PARTICIPATION] = previous_participation
+                .updated_list_root(
+                    roots[field_index::PREVIOUS_EPOCH_PARTICIPATION],
+                    PARTICIPATION_LIST_DEPTH,
+                    (
+                        &packed_leaves(&pre_state.previous_epoch_participation),
+                        pre_state.previous_epoch_participation.len(),
+                    ),
+                    (
+                        &packed_leaves(&post_state.previous_epoch_participation),
+                        post_state.previous_epoch_participation.len(),
+                    ),
+                    |leaf| *leaf,
+                )?;
+        }
+        if pre_state.current_epoch_participation != post_state.current_epoch_participation {
+            roots[field_index::CURRENT_EPOCH_PARTICIPATION] = current_participation
+                .updated_list_root(
+                    roots[field_index::CURRENT_EPOCH_PARTICIPATION],
+                    PARTICIPATION_LIST_DEPTH,
+                    (
+                        &packed_leaves(&pre_state.current_epoch_participation),
+                        pre_state.current_epoch_participation.len(),
+                    ),
+                    (
+                        &packed_leaves(&post_state.current_epoch_participation),
+                        post_state.current_epoch_participation.len(),
+                    ),
+                    |leaf| *leaf,
+                )?;
+        }
+        if pre_state.inactivity_scores != post_state.inactivity_scores {
+            roots[field_index::INACTIVITY_SCORES] = inactivity.updated_list_root(
+                roots[field_index::INACTIVITY_SCORES],
+                U64_LIST_DEPTH,
+                (
+                    &packed_leaves(&u64_bytes(&pre_state.inactivity_scores)),
+                    pre_state.inactivity_scores.len(),
+                ),
+                (
+                    &packed_leaves(&u64_bytes(&post_state.inactivity_scores)),
+                    post_state.inactivity_scores.len(),
+                ),
+                |leaf| *leaf,
+            )?;
+        }
+
+        Some(root_from_field_roots(&roots))
+    }
+
+    /// Encodes the cache as the raw stdin buffer of the incremental merkleization mode: the field
+    /// roots, then every subtree as its leaf count as a little-endian `u32` followed by its
+    /// layers, leaves first.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.field_roots.iter().flat_map(|root| root.0).collect();
+        for subtree in &self.subtrees {
+            subtree.encode(&mut bytes);
+        }
+        bytes
+    }
+
+    /// Decodes the raw stdin buffer written by [`StateRootCache::encode`].
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let (field_roots, mut rest) = bytes.split_at_checked(BEACON_STATE_FIELD_COUNT * 32)?;
+        let mut subtrees = Vec::with_capacity(SUBTREE_FIELDS.len());
+        for _ in SUBTREE_FIELDS {
+            let (subtree, remaining) = SubtreeCache::decode(rest)?;
+            subtrees.push(subtree);
+            rest = remaining;
+        }
+        if !rest.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            field_roots: field_roots
+                .chunks_exact(32)
+                .map(Hash256::from_slice)
+                .collect(),
+            subtrees,
+        })
+    }
+}
+
+fn decoded_field_root<T: ssz::Decode + TreeHash>(
+    lazy_state: &LazyBeaconState,
+    index: usize,
+    _projection: fn(&BeaconState) -> &T,
+) -> Result<Hash256, DecodeError> {
+    Ok(lazy_state.decode_field::<T>(index)?.tree_hash_root())
+}
+
+/// Root of `bytes` packed into a tree of `depth`. Only the smallest subtree holding the bytes is
+/// hashed; it is then paired with zero subtrees, whose leaf counts overflow the guest's `usize`.
+fn packed_root(bytes: &[u8], depth: usize) -> Hash256 {
+    let subtree_depth = bytes
+        .len()
+        .div_ceil(32)
+        .next_power_of_two()
+        .trailing_zeros() as usize;
+    pad_to_depth(merkle_root(bytes, 1 << subtree_depth), subtree_depth, depth)
+}
+
+/// Root of a field whose hashing is not [`FieldHashing::Decoded`], from its SSZ bytes.
+fn packed_field_root(bytes: &[u8], hashing: FieldHashing) -> Result<Hash256, DecodeError> {
+    match hashing {
+        Basic => {
+            // Fixed-size fields are at most 32 bytes long
+            let mut root = Hash256::ZERO;
+            root[..bytes.len()].copy_from_slice(bytes);
+            Ok(root)
+        }
+        PackedVector => Ok(merkle_root(bytes, 0)),
+        PackedList { item_len, depth } => {
+            if bytes.len() % item_len != 0 {
+                return Err(DecodeError::InvalidByteLength {
+                    len: bytes.len(),
+                    expected: bytes.len() / item_len * item_len,
+                });
+            }
+            Ok(mix_in_length(
+                &packed_root(bytes, depth),
+                bytes.len() / item_len,
+            ))
+        }
+        Decoded => unreachable!("decoded fields are hashed by type"),
+    }
+}
+
+macro_rules! lazy_field_roots {
+    ($lazy_state:ident, $known:ident; $($field:ident),*) => {{
+        let mut index = 0;
+        let mut roots = Vec::with_capacity(BEACON_STATE_FIELD_COUNT);
+        $(
+            let root = match ($known.iter().find(|(known, _)| *known == index), FIELD_HASHING[index]) {
+                (Some((_, root)), _) => *root,
+                (None, Decoded) => {
+                    decoded_field_root($lazy_state, index, |state: &BeaconState| &state.$field)?
+                }
+                (None, hashing) => packed_field_root($lazy_state.field_bytes(index), hashing)?,
+            };
+            roots.push(root);
+            index += 1;
+        )*
+        let _ = index;
+        roots
+    }};
+}
+
+/// Computes the field roots of a lazily decoded state, taking the roots in `known` as given.
+/// Basic values and lists and vectors of them are hashed straight from their SSZ bytes; only
+/// containers and lists of containers are decoded.
+pub fn lazy_field_roots(
+    lazy_state: &LazyBeaconState,
+    known: &[(usize, Hash256)],
+) -> Result<Vec<Hash256>, DecodeError> {
+    Ok(for_each_beacon_state_field!(lazy_field_roots!(
+        lazy_state, known
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_root_matches_merkle_root() {
+        let bytes: Vec<u8> = (0..=200).collect();
+        for depth in 3..=6 {
+            assert_eq!(packed_root(&bytes, depth), merkle_root(&bytes, 1 << depth));
+        }
+        assert_eq!(packed_root(&[], 4), merkle_root(&[], 16));
+    }
+
+    #[test]
+    fn packed_list_root_mixes_in_item_count() {
+        let bytes = [7; 24];
+        let hashing = PackedList {
+            item_len: 8,
+            depth: 2,
+        };
+        let expected = mix_in_length(&merkle_root(&bytes, 4), 3);
+        assert_eq!(packed_field_root(&bytes, hashing).unwrap(), expected);
+        assert!(packed_field_root(&bytes[..20], hashing).is_err());
+    }
+
+    #[test]
+    fn basic_root_is_padded_bytes() {
+        let root = packed_field_root(&42u64.to_le_bytes(), Basic).unwrap();
+        assert_eq!(root, 42u64.tree_hash_root());
+    }
+
+    fn u64_list_root(values: &[u64]) -> Hash256 {
+        mix_in_length(
+            &packed_root(&u64_bytes(values), U64_LIST_DEPTH),
+            values.len(),
+        )
+    }
+
+    fn updated_u64_list_root(pre: &[u64], post: &[u64]) -> Option<Hash256> {
+        let mut subtree = SubtreeCache::from_leaves(packed_leaves(&u64_bytes(pre)));
+        subtree.updated_list_root(
+            u64_list_root(pre),
+            U64_LIST_DEPTH,
+            (&packed_leaves(&u64_bytes(pre)), pre.len()),
+            (&packed_leaves(&u64_bytes(post)), post.len()),
+            |leaf| *leaf,
+        )
+    }
+
+    #[test]
+    fn updated_list_root_matches_a_full_rebuild() {
+        let pre: Vec<u64> = (0..30).collect();
+        let mut changed = pre.clone();
+        changed[3] = 100;
+        changed[21] = 200;
+        let grown: Vec<u64> = (0..31).collect();
+        let outgrown: Vec<u64> = (0..40).collect();
+        for post in [
+            &pre[..],
+            &changed[..],
+            &grown[..],
+            &outgrown[..],
+            &pre[..9],
+            &[],
+        ] {
+            assert_eq!(updated_u64_list_root(&pre, post), Some(u64_list_root(post)));
+        }
+    }
+
+    #[test]
+    fn updated_list_root_rejects_stale_roots() {
+        let pre: Vec<u64> = (0..30).collect();
+        let mut subtree = SubtreeCache::from_leaves(packed_leaves(&u64_bytes(&pre)));
+        let post = [&pre[..29], &[7]].concat();
+        let updated = subtree.clone().updated_list_root(
+            Hash256::ZERO,
+            U64_LIST_DEPTH,
+            (&packed_leaves(&u64_bytes(&pre)), pre.len()),
+            (&packed_leaves(&u64_bytes(&post)), post.len()),
+            |leaf| *leaf,
+        );
+        assert_eq!(updated, None);
+
+        let stale_pre = [&pre[..29], &[8]].concat();
+        let updated = subtree.updated_list_root(
+            u64_list_root(&pre),
+            U64_LIST_DEPTH,
+            (&packed_leaves(&u64_bytes(&stale_pre)), pre.len()),
+            (&packed_leaves(&u64_bytes(&post)), post.len()),
+            |leaf| *leaf,
+        );
+        assert_eq!(updated, None);
+    }
+
+    #[test]
+    fn state_root_cache_round_trips() {
+        let state = BeaconState::default();
+        let cache = StateRootCache::from_state(&state);
+        assert_eq!(cache.root(), state.tree_hash_root());
+        assert_eq!(StateRootCache::decode(&cache.encode()), Some(cache.clone()));
+        assert_eq!(StateRootCache::decode(&cache.encode()[1..]), None);
+    }
+}
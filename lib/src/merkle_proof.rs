@@ -0,0 +1,42 @@
+//! Single Merkle proofs of the `single_merkle_proof` spec tests, from the `light_client` and
+//! `merkle_proof` runners.
+//!
+//! The guest hashes the case's object, verifies the branch of `leaf` at its generalized index
+//! against that root and commits the root, leaf and generalized index.
+
+use std::path::Path;
+
+use alloy_primitives::B256;
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::file::read_file;
+use crate::witness::is_valid_merkle_branch;
+
+/// Contents of a case's `proof.yaml`.
+#[derive(Deserialize, Debug)]
+pub struct Proof {
+    pub leaf: B256,
+    /// Generalized index of `leaf` in the object.
+    pub leaf_index: u64,
+    pub branch: Vec<B256>,
+}
+
+/// Loads `proof.yaml` from `case_dir`.
+pub fn load_proof(case_dir: &Path) -> Result<Proof> {
+    let path = case_dir.join("proof.yaml");
+    let bytes = read_file(&path)?;
+
+    serde_yaml::from_slice(&bytes).map_err(|source| Error::Yaml { path, source })
+}
+
+/// Checks that `leaf` is at generalized index `gindex` of the tree with root `root`, whose depth
+/// is `floorlog2(gindex)`.
+pub fn is_valid_gindex_branch(leaf: B256, branch: &[B256], gindex: u64, root: B256) -> bool {
+    if gindex == 0 {
+        return false;
+    }
+    let depth = gindex.ilog2() as usize;
+
+    is_valid_merkle_branch(leaf, branch, depth, gindex % (1 << depth), root)
+}
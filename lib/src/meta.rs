@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use alloy_primitives::{B256, FixedBytes};
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::file::read_file;
+
+/// `bls_setting` of a consensus-spec-tests case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlsSetting {
+    /// The case does not care whether signatures are verified.
+    #[default]
+    Optional,
+    /// Signatures must be verified.
+    Required,
+    /// Signatures must not be verified; the case carries placeholder signatures.
+    Ignored,
+}
+
+/// Contents of a case's `meta.yaml`. Unknown keys are ignored.
+#[derive(Deserialize, Debug, Default)]
+pub struct CaseMeta {
+    #[serde(default)]
+    bls_setting: u8,
+    /// Number of `blocks_<i>.ssz_snappy` files of block sequence cases.
+    #[serde(default)]
+    blocks_count: Option<usize>,
+    /// Epoch at which transition cases switch to the next fork.
+    #[serde(default)]
+    fork_epoch: Option<u64>,
+    /// Index of the last pre-fork block of transition cases, absent when every block is post-fork.
+    #[serde(default)]
+    fork_block: Option<usize>,
+    /// Genesis validators root of light client sync cases, part of their signature domain.
+    #[serde(default)]
+    genesis_validators_root: Option<B256>,
+    /// Block root the bootstrap of light client sync cases must match.
+    #[serde(default)]
+    trusted_block_root: Option<B256>,
+    /// Fork digest of the store of light client sync cases.
+    #[serde(default)]
+    store_fork_digest: Option<FixedBytes<4>>,
+    /// Number of `updates_<i>.ssz_snappy` files of light client update ranking cases.
+    #[serde(default)]
+    updates_count: Option<usize>,
+}
+
+impl CaseMeta {
+    pub fn bls_setting(&self) -> BlsSetting {
+        match self.bls_setting {
+            1 => BlsSetting::Required,
+            2 => BlsSetting::Ignored,
+            _ => BlsSetting::Optional,
+        }
+    }
+
+    pub fn blocks_count(&self) -> usize {
+        self.blocks_count.unwrap_or(0)
+    }
+
+    pub fn fork_epoch(&self) -> Option<u64> {
+        self.fork_epoch
+    }
+
+    pub fn fork_block(&self) -> Option<usize> {
+        self.fork_block
+    }
+
+    pub fn genesis_validators_root(&self) -> Option<B256> {
+        self.genesis_validators_root
+    }
+
+    pub fn trusted_block_root(&self) -> Option<B256> {
+        self.trusted_block_root
+    }
+
+    pub fn store_fork_digest(&self) -> Option<FixedBytes<4>> {
+        self.store_fork_digest
+    }
+
+    pub fn updates_count(&self) -> usize {
+        self.updates_count.unwrap_or(0)
+    }
+}
+
+/// Loads `meta.yaml` from `case_dir`, falling back to the defaults when the case has none.
+pub fn load_meta(case_dir: &Path) -> Result<CaseMeta> {
+    let path = case_dir.join("meta.yaml");
+    if !path.exists() {
+        return Ok(CaseMeta::default());
+    }
+
+    let bytes = read_file(&path)?;
+    serde_yaml::from_slice(&bytes).map_err(|source| Error::Yaml { path, source })
+}
@@ -0,0 +1,68 @@
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+
+use crate::fork_choice::StoreSnapshot;
+use crate::frame::Phase;
+use crate::light_client::LightClientSnapshot;
+use crate::state_query::QueryAnswer;
+
+/// Stage of the guest's input handling that rejected the input.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputStage {
+    ReadHeader,
+    ReadPreState,
+    DecompressPreState,
+    DeserializePreState,
+    ReadOperationInput,
+    DeserializeOperation,
+    ValidateFieldRoots,
+    ValidateWitness,
+    /// A light client bootstrap or update failed validation against the store.
+    ValidateLightClientUpdate,
+    /// The branch of a single Merkle proof does not open the object's root.
+    ValidateMerkleProof,
+    /// A queried validator index is beyond the state's validator registry.
+    ValidateStateQuery,
+    /// The operation is valid but this guest build cannot run it, e.g. it needs another fork.
+    UnsupportedOperation,
+    /// A step did not have the outcome consensus-spec-tests expects, e.g. a failed fork upgrade or
+    /// an accepted fork choice block the case marks invalid.
+    ApplyOperation,
+}
+
+/// Public values committed by the guest.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum GuestOutput {
+    /// Root of the state after the operation was applied.
+    StateRoot(B256),
+    /// Roots of the state before and after the operation was applied, committed when the
+    /// pre-state root is derived from host-supplied roots (incremental merkleization or a
+    /// witness) rather than from the full pre-state.
+    StateTransition {
+        /// Root the host-supplied roots merkleize to. The guest does not check it against the
+        /// pre-state bytes; the host compares it with the pre-state root.
+        supplied_pre_state_root: B256,
+        state_root: B256,
+    },
+    /// Hash tree root of a standalone container of an `ssz_static` case.
+    SszRoot(B256),
+    /// Digest of the reward and penalty deltas of the pre-state, see [`crate::rewards`].
+    RewardsDeltas(B256),
+    /// Digest of the shuffled index mapping of a `shuffling` case, see [`crate::shuffling`].
+    ShuffleMapping(B256),
+    /// Store values at every `checks` step of a `fork_choice` case, see [`crate::fork_choice`].
+    ForkChoice(Vec<StoreSnapshot>),
+    /// Store headers after every step of a `light_client/sync` case, see [`crate::light_client`].
+    LightClientSync(Vec<LightClientSnapshot>),
+    /// Whether the updates of a `light_client/update_ranking` case are ordered best first.
+    LightClientUpdateRanking(bool),
+    /// Root of the object of a `single_merkle_proof` case, with the leaf and generalized index
+    /// whose branch into it was verified, see [`crate::merkle_proof`].
+    MerkleProof { root: B256, leaf: B256, gindex: u64 },
+    /// Requested field of the queried validators with the root of the state they were read from.
+    StateQuery(QueryAnswer),
+    /// The guest exited after the given phase, as requested by the header, without a state root.
+    Stopped(Phase),
+    /// The inputs could not be decoded, so no state transition was executed.
+    InvalidInput(InputStage),
+}
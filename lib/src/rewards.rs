@@ -0,0 +1,57 @@
+//! Reward and penalty deltas of the `rewards` spec tests.
+//!
+//! The guest computes the deltas of each component from the pre-state and commits a digest of
+//! them instead of a post-state root, so the rewards math can be measured on its own.
+
+use alloy_primitives::B256;
+use ethereum_hashing::hash;
+use ream_consensus::electra::beacon_state::BeaconState;
+use ssz::Encode;
+use ssz_derive::{Decode, Encode};
+
+/// `TIMELY_SOURCE_FLAG_INDEX`, `TIMELY_TARGET_FLAG_INDEX` and `TIMELY_HEAD_FLAG_INDEX`.
+const FLAG_INDICES: [u8; 3] = [0, 1, 2];
+
+/// Delta files of a `rewards` case, in the order their encodings are digested.
+pub const DELTAS_FILES: [&str; 4] = [
+    "source_deltas.ssz_snappy",
+    "target_deltas.ssz_snappy",
+    "head_deltas.ssz_snappy",
+    "inactivity_penalty_deltas.ssz_snappy",
+];
+
+/// SSZ `Deltas` container of the spec tests.
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Eq)]
+pub struct Deltas {
+    pub rewards: Vec<u64>,
+    pub penalties: Vec<u64>,
+}
+
+/// Computes the deltas of every file of [`DELTAS_FILES`] from `state`.
+pub fn compute_deltas(state: &BeaconState) -> anyhow::Result<Vec<Deltas>> {
+    let mut deltas = Vec::with_capacity(DELTAS_FILES.len());
+    for flag_index in FLAG_INDICES {
+        let (rewards, penalties) = state.get_flag_index_deltas(flag_index)?;
+        deltas.push(Deltas { rewards, penalties });
+    }
+    let (rewards, penalties) = state.get_inactivity_penalty_deltas()?;
+    deltas.push(Deltas { rewards, penalties });
+
+    Ok(deltas)
+}
+
+/// SHA-256 of the concatenated SSZ encodings, which the host recomputes from the spec files.
+pub fn deltas_digest<T: AsRef<[u8]>>(encodings: &[T]) -> B256 {
+    let bytes: Vec<u8> = encodings
+        .iter()
+        .flat_map(|encoding| encoding.as_ref().iter().copied())
+        .collect();
+
+    B256::from_slice(&hash(&bytes))
+}
+
+/// Digest of deltas computed in the guest.
+pub fn computed_deltas_digest(deltas: &[Deltas]) -> B256 {
+    let encodings: Vec<Vec<u8>> = deltas.iter().map(Encode::as_ssz_bytes).collect();
+    deltas_digest(&encodings)
+}
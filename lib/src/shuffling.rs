@@ -0,0 +1,46 @@
+//! Index mappings of the `shuffling/core/shuffle` spec tests.
+//!
+//! The guest shuffles every index of `0..count` for the case's seed and commits a digest of the
+//! resulting mapping, so `compute_shuffled_index` can be measured without any state around it.
+
+use std::path::Path;
+
+use alloy_primitives::B256;
+use ethereum_hashing::hash;
+use ream_consensus_misc::misc::compute_shuffled_index;
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::file::read_file;
+
+/// Contents of a case's `mapping.yaml`.
+#[derive(Deserialize, Debug)]
+pub struct Mapping {
+    pub seed: B256,
+    pub count: u64,
+    pub mapping: Vec<u64>,
+}
+
+/// Loads `mapping.yaml` from `case_dir`.
+pub fn load_mapping(case_dir: &Path) -> Result<Mapping> {
+    let path = case_dir.join("mapping.yaml");
+    let bytes = read_file(&path)?;
+
+    serde_yaml::from_slice(&bytes).map_err(|source| Error::Yaml { path, source })
+}
+
+/// Shuffled position of every index of `0..count`, so that `mapping[i]` is
+/// `compute_shuffled_index(i, count, seed)`.
+pub fn compute_mapping(seed: B256, count: u64) -> anyhow::Result<Vec<u64>> {
+    (0..count as usize)
+        .map(|index| Ok(compute_shuffled_index(index, count as usize, seed)? as u64))
+        .collect()
+}
+
+/// SHA-256 of the little-endian `u64` encodings of the mapping, which the host recomputes from
+/// `mapping.yaml`.
+pub fn mapping_digest(mapping: &[u64]) -> B256 {
+    let bytes: Vec<u8> = mapping.iter().flat_map(|index| index.to_le_bytes()).collect();
+
+    B256::from_slice(&hash(&bytes))
+}
@@ -0,0 +1,43 @@
+//! Constants and helpers of the consensus specs shared by the modules that implement parts of the
+//! specs ream does not expose.
+
+use alloy_primitives::B256;
+use ethereum_hashing::hash32_concat;
+
+pub const SLOTS_PER_EPOCH: u64 = 32;
+pub const SLOTS_PER_HISTORICAL_ROOT: u64 = 8192;
+
+/// Fork versions of the mainnet config up to Electra, in activation order.
+pub const MAINNET_FORK_VERSIONS: [[u8; 4]; 6] = [
+    [0, 0, 0, 0],
+    [1, 0, 0, 0],
+    [2, 0, 0, 0],
+    [3, 0, 0, 0],
+    [4, 0, 0, 0],
+    [5, 0, 0, 0],
+];
+
+/// Root of the `ForkData` container, whose version chunk is right-padded with zeros.
+pub fn compute_fork_data_root(fork_version: [u8; 4], genesis_validators_root: B256) -> B256 {
+    let mut version_chunk = [0; 32];
+    version_chunk[..4].copy_from_slice(&fork_version);
+
+    B256::from_slice(&hash32_concat(&version_chunk, genesis_validators_root.as_slice()))
+}
+
+pub fn compute_domain(
+    domain_type: [u8; 4],
+    fork_version: [u8; 4],
+    genesis_validators_root: B256,
+) -> B256 {
+    let fork_data_root = compute_fork_data_root(fork_version, genesis_validators_root);
+    let mut domain = [0; 32];
+    domain[..4].copy_from_slice(&domain_type);
+    domain[4..].copy_from_slice(&fork_data_root[..28]);
+
+    B256::from(domain)
+}
+
+pub fn compute_signing_root(object_root: B256, domain: B256) -> B256 {
+    B256::from_slice(&hash32_concat(object_root.as_slice(), domain.as_slice()))
+}
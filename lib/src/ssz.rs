@@ -1,5 +1,96 @@
+use std::ops::Range;
+
+use ream_consensus::electra::beacon_state::BeaconState;
 use ssz::DecodeError;
 
+use crate::merkle::{BEACON_STATE_FIELD_COUNT, for_each_beacon_state_field};
+
 pub fn from_ssz_bytes<T: ssz::Decode>(ssz_bytes: &[u8]) -> Result<T, DecodeError> {
     T::from_ssz_bytes(ssz_bytes)
 }
+
+pub(crate) const BYTES_PER_LENGTH_OFFSET: usize = 4;
+
+/// Fixed length of `T` in SSZ, or `None` if it is variable-size. The projection only pins `T` to
+/// the type of a `BeaconState` field.
+fn fixed_len<T: ssz::Decode>(_projection: fn(&BeaconState) -> &T) -> Option<usize> {
+    T::is_ssz_fixed_len().then(T::ssz_fixed_len)
+}
+
+macro_rules! field_fixed_lens {
+    (; $($field:ident),*) => {
+        [$(fixed_len(|state: &BeaconState| &state.$field)),*]
+    };
+}
+
+/// Fixed length in SSZ of every `BeaconState` field, in declaration order, or `None` for the
+/// variable-size fields, which take an offset in the fixed-size part.
+pub(crate) fn beacon_state_fixed_lens() -> [Option<usize>; BEACON_STATE_FIELD_COUNT] {
+    for_each_beacon_state_field!(field_fixed_lens!())
+}
+
+/// Lazy, offset-based view over the SSZ bytes of an Electra `BeaconState`.
+///
+/// Construction only walks the fixed-size part to locate every field; a field is decoded when it
+/// is first asked for, so operations that touch a few fields skip decoding the rest.
+pub struct LazyBeaconState<'a> {
+    bytes: &'a [u8],
+    ranges: Vec<Range<usize>>,
+}
+
+impl<'a> LazyBeaconState<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        let fixed_lens = beacon_state_fixed_lens();
+        let mut ranges = vec![0..0; BEACON_STATE_FIELD_COUNT];
+        let mut variable_fields = Vec::new();
+        let mut position = 0;
+
+        for (index, fixed_len) in fixed_lens.iter().enumerate() {
+            let len = fixed_len.unwrap_or(BYTES_PER_LENGTH_OFFSET);
+            let field_bytes = bytes.get(position..position + len).ok_or(
+                DecodeError::InvalidByteLength {
+                    len: bytes.len(),
+                    expected: position + len,
+                },
+            )?;
+            match fixed_len {
+                Some(_) => ranges[index] = position..position + len,
+                None => {
+                    let offset = u32::from_le_bytes(field_bytes.try_into().unwrap()) as usize;
+                    variable_fields.push((index, offset));
+                }
+            }
+            position += len;
+        }
+
+        if let Some((_, first_offset)) = variable_fields.first() {
+            if *first_offset != position {
+                return Err(DecodeError::OffsetIntoFixedPortion(*first_offset));
+            }
+        }
+        for (i, (index, start)) in variable_fields.iter().enumerate() {
+            let end = variable_fields
+                .get(i + 1)
+                .map_or(bytes.len(), |(_, offset)| *offset);
+            if end > bytes.len() {
+                return Err(DecodeError::OffsetOutOfBounds(end));
+            }
+            if *start > end {
+                return Err(DecodeError::OffsetsAreDecreasing(end));
+            }
+            ranges[*index] = *start..end;
+        }
+
+        Ok(Self { bytes, ranges })
+    }
+
+    /// SSZ bytes of the field at `index`, in declaration order.
+    pub fn field_bytes(&self, index: usize) -> &'a [u8] {
+        &self.bytes[self.ranges[index].clone()]
+    }
+
+    /// Decodes the field at `index` as `T`.
+    pub fn decode_field<T: ssz::Decode>(&self, index: usize) -> Result<T, DecodeError> {
+        T::from_ssz_bytes(self.field_bytes(index))
+    }
+}
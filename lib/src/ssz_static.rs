@@ -0,0 +1,112 @@
+//! Containers of the `ssz_static` spec tests that the guest can decode and hash on their own, also
+//! used for the objects of the `single_merkle_proof` spec tests.
+
+use std::path::Path;
+
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::file::read_file;
+
+/// Container type of an `ssz_static/<Type>` case, carried in the `step` byte of the frame header.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SszType {
+    Attestation,
+    AttesterSlashing,
+    BeaconBlock,
+    BeaconBlockHeader,
+    BeaconState,
+    Deposit,
+    ExecutionPayload,
+    ProposerSlashing,
+    SignedBeaconBlock,
+    SignedBLSToExecutionChange,
+    SignedVoluntaryExit,
+    SyncAggregate,
+    AttestationData,
+    Checkpoint,
+    DepositData,
+    Eth1Data,
+    Fork,
+    SyncCommittee,
+    Validator,
+    // Appended to keep the `u8` values of the types above
+    BeaconBlockBody,
+}
+
+impl SszType {
+    pub const ALL: [SszType; 20] = [
+        SszType::Attestation,
+        SszType::AttesterSlashing,
+        SszType::BeaconBlock,
+        SszType::BeaconBlockHeader,
+        SszType::BeaconState,
+        SszType::Deposit,
+        SszType::ExecutionPayload,
+        SszType::ProposerSlashing,
+        SszType::SignedBeaconBlock,
+        SszType::SignedBLSToExecutionChange,
+        SszType::SignedVoluntaryExit,
+        SszType::SyncAggregate,
+        SszType::AttestationData,
+        SszType::Checkpoint,
+        SszType::DepositData,
+        SszType::Eth1Data,
+        SszType::Fork,
+        SszType::SyncCommittee,
+        SszType::Validator,
+        SszType::BeaconBlockBody,
+    ];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    /// Directory name of the type under `ssz_static`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SszType::Attestation => "Attestation",
+            SszType::AttesterSlashing => "AttesterSlashing",
+            SszType::BeaconBlock => "BeaconBlock",
+            SszType::BeaconBlockHeader => "BeaconBlockHeader",
+            SszType::BeaconState => "BeaconState",
+            SszType::Deposit => "Deposit",
+            SszType::ExecutionPayload => "ExecutionPayload",
+            SszType::ProposerSlashing => "ProposerSlashing",
+            SszType::SignedBeaconBlock => "SignedBeaconBlock",
+            SszType::SignedBLSToExecutionChange => "SignedBLSToExecutionChange",
+            SszType::SignedVoluntaryExit => "SignedVoluntaryExit",
+            SszType::SyncAggregate => "SyncAggregate",
+            SszType::AttestationData => "AttestationData",
+            SszType::Checkpoint => "Checkpoint",
+            SszType::DepositData => "DepositData",
+            SszType::Eth1Data => "Eth1Data",
+            SszType::Fork => "Fork",
+            SszType::SyncCommittee => "SyncCommittee",
+            SszType::Validator => "Validator",
+            SszType::BeaconBlockBody => "BeaconBlockBody",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|ssz_type| ssz_type.name() == name)
+    }
+}
+
+/// Contents of a case's `roots.yaml`.
+#[derive(Deserialize, Debug)]
+struct Roots {
+    root: B256,
+}
+
+/// Loads the expected hash tree root from `roots.yaml` in `case_dir`.
+pub fn load_root(case_dir: &Path) -> Result<B256> {
+    let path = case_dir.join("roots.yaml");
+    let bytes = read_file(&path)?;
+    let roots: Roots =
+        serde_yaml::from_slice(&bytes).map_err(|source| Error::Yaml { path, source })?;
+
+    Ok(roots.root)
+}
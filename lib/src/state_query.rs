@@ -0,0 +1,129 @@
+//! Queries of validator fields against a state root.
+//!
+//! The guest reads one field of each queried validator from the state and commits a
+//! [`QueryAnswer`] with the state root, so a claim such as "validator `i` had balance `b` at
+//! state root `r`" can be checked against the proof alone, without the state.
+
+use alloy_primitives::B256;
+use ream_consensus::{electra::beacon_state::BeaconState, validator::Validator};
+use serde::{Deserialize, Serialize};
+
+/// `FAR_FUTURE_EPOCH` of the consensus specs.
+const FAR_FUTURE_EPOCH: u64 = u64::MAX;
+
+/// Validator field a query reads, carried in the step byte of the frame.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ValidatorField {
+    /// Entry of `state.balances`, in Gwei.
+    Balance,
+    /// `effective_balance` of the validator record, in Gwei.
+    EffectiveBalance,
+    /// [`ValidatorStatus`] at the state's current epoch, as `u8`.
+    Status,
+}
+
+impl ValidatorField {
+    pub const ALL: [ValidatorField; 3] = [
+        ValidatorField::Balance,
+        ValidatorField::EffectiveBalance,
+        ValidatorField::Status,
+    ];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+}
+
+/// Validator status of the beacon node API, derived from the validator record and an epoch.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ValidatorStatus {
+    PendingInitialized,
+    PendingQueued,
+    ActiveOngoing,
+    ActiveExiting,
+    ActiveSlashed,
+    ExitedUnslashed,
+    ExitedSlashed,
+    WithdrawalPossible,
+    WithdrawalDone,
+}
+
+impl ValidatorStatus {
+    pub const ALL: [ValidatorStatus; 9] = [
+        ValidatorStatus::PendingInitialized,
+        ValidatorStatus::PendingQueued,
+        ValidatorStatus::ActiveOngoing,
+        ValidatorStatus::ActiveExiting,
+        ValidatorStatus::ActiveSlashed,
+        ValidatorStatus::ExitedUnslashed,
+        ValidatorStatus::ExitedSlashed,
+        ValidatorStatus::WithdrawalPossible,
+        ValidatorStatus::WithdrawalDone,
+    ];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    /// Status of `validator` at `epoch`.
+    pub fn of(validator: &Validator, epoch: u64) -> Self {
+        if validator.activation_epoch > epoch {
+            if validator.activation_eligibility_epoch == FAR_FUTURE_EPOCH {
+                ValidatorStatus::PendingInitialized
+            } else {
+                ValidatorStatus::PendingQueued
+            }
+        } else if epoch < validator.exit_epoch {
+            if validator.exit_epoch == FAR_FUTURE_EPOCH {
+                ValidatorStatus::ActiveOngoing
+            } else if validator.slashed {
+                ValidatorStatus::ActiveSlashed
+            } else {
+                ValidatorStatus::ActiveExiting
+            }
+        } else if epoch < validator.withdrawable_epoch {
+            if validator.slashed {
+                ValidatorStatus::ExitedSlashed
+            } else {
+                ValidatorStatus::ExitedUnslashed
+            }
+        } else if validator.effective_balance != 0 {
+            ValidatorStatus::WithdrawalPossible
+        } else {
+            ValidatorStatus::WithdrawalDone
+        }
+    }
+}
+
+/// Public values of a state query: the requested field of every queried validator, in the order
+/// of `indices`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct QueryAnswer {
+    pub state_root: B256,
+    pub field: ValidatorField,
+    pub indices: Vec<u64>,
+    pub values: Vec<u64>,
+}
+
+/// Reads `field` of the validators at `indices`, or `None` if an index is out of range.
+pub fn query_validators(
+    state: &BeaconState,
+    field: ValidatorField,
+    indices: &[u64],
+) -> Option<Vec<u64>> {
+    let epoch = state.get_current_epoch();
+    indices
+        .iter()
+        .map(|&index| {
+            let validator = state.validators.get(index as usize)?;
+            let value = match field {
+                ValidatorField::Balance => *state.balances.get(index as usize)?,
+                ValidatorField::EffectiveBalance => validator.effective_balance,
+                ValidatorField::Status => ValidatorStatus::of(validator, epoch) as u64,
+            };
+            Some(value)
+        })
+        .collect()
+}
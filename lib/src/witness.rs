@@ -0,0 +1,357 @@
+//! Stateless witnesses: an operation is applied to a Merkle multiproof of the pre-state instead
+//! of the full `BeaconState`.
+//!
+//! The multiproof consists of all top-level field roots (which merkleize to the pre-state root),
+//! the full values of the small fields the operation touches and single-leaf branches into the
+//! large vectors and lists it touches. `process_slot` and `process_bls_to_execution_change` have
+//! a witness so far; the latter opens a single validator of the registry.
+
+use ethereum_hashing::{hash, hash32_concat};
+use ream_consensus::{
+    beacon_block_header::BeaconBlockHeader,
+    bls_to_execution_change::SignedBLSToExecutionChange, electra::beacon_state::BeaconState,
+    validator::Validator,
+};
+use ream_bls::traits::Verifiable;
+use ssz::Encode;
+use ssz_derive::{Decode, Encode};
+use tree_hash::{Hash256, TreeHash, mix_in_length};
+
+use ssz::DecodeError;
+
+use crate::merkle::{
+    BEACON_STATE_FIELD_COUNT, field_index, field_roots, lazy_field_roots, root_from_field_roots,
+};
+use crate::spec::{
+    MAINNET_FORK_VERSIONS, SLOTS_PER_HISTORICAL_ROOT, compute_domain, compute_signing_root,
+};
+use crate::ssz::LazyBeaconState;
+
+/// Depth of `block_roots` and `state_roots`, `log2(SLOTS_PER_HISTORICAL_ROOT)`.
+pub const HISTORICAL_ROOTS_DEPTH: usize = 13;
+/// Depth of `validators`, `log2(VALIDATOR_REGISTRY_LIMIT)`.
+pub const VALIDATORS_DEPTH: usize = 40;
+
+pub const DOMAIN_BLS_TO_EXECUTION_CHANGE: [u8; 4] = [10, 0, 0, 0];
+pub const BLS_WITHDRAWAL_PREFIX: u8 = 0;
+pub const ETH1_ADDRESS_WITHDRAWAL_PREFIX: u8 = 1;
+
+/// Witness of the pre-state for `process_slot`.
+#[derive(Debug, Encode, Decode)]
+pub struct ProcessSlotWitness {
+    pub field_roots: Vec<Hash256>,
+    pub slot: u64,
+    pub latest_block_header: BeaconBlockHeader,
+    pub state_roots_entry: Hash256,
+    pub state_roots_branch: Vec<Hash256>,
+    pub block_roots_entry: Hash256,
+    pub block_roots_branch: Vec<Hash256>,
+}
+
+/// Checks that `leaf` is at `index` of a tree of `depth` with root `root`.
+pub fn is_valid_merkle_branch(
+    leaf: Hash256,
+    branch: &[Hash256],
+    depth: usize,
+    index: u64,
+    root: Hash256,
+) -> bool {
+    branch.len() == depth && branch_root(leaf, branch, index) == root
+}
+
+/// Recomputes the root of the tree in which `leaf` sits at `index` with siblings `branch`.
+pub fn branch_root(leaf: Hash256, branch: &[Hash256], index: u64) -> Hash256 {
+    let mut value = leaf;
+    for (height, sibling) in branch.iter().enumerate() {
+        value = if (index >> height) & 1 == 1 {
+            Hash256::from_slice(&hash32_concat(sibling.as_slice(), value.as_slice()))
+        } else {
+            Hash256::from_slice(&hash32_concat(value.as_slice(), sibling.as_slice()))
+        };
+    }
+    value
+}
+
+/// Builds the branch of `index` in the tree over `leaves`, whose length must be a power of two.
+pub fn vector_branch(leaves: &[Hash256], index: usize) -> Vec<Hash256> {
+    let mut layer = leaves.to_vec();
+    let mut index = index;
+    let mut branch = Vec::new();
+    while layer.len() > 1 {
+        branch.push(layer[index ^ 1]);
+        layer = layer
+            .chunks_exact(2)
+            .map(|pair| Hash256::from_slice(&hash32_concat(pair[0].as_slice(), pair[1].as_slice())))
+            .collect();
+        index >>= 1;
+    }
+    branch
+}
+
+/// Builds the branch of `index` in a tree of `depth` whose first leaves are `leaves` and whose
+/// other leaves are zero, as in the tree of an SSZ list.
+pub fn list_branch(leaves: &[Hash256], index: usize, depth: usize) -> Vec<Hash256> {
+    let mut layer = leaves.to_vec();
+    let mut index = index;
+    let mut zero_subtree = Hash256::ZERO;
+    let mut branch = Vec::with_capacity(depth);
+    for _ in 0..depth {
+        branch.push(layer.get(index ^ 1).copied().unwrap_or(zero_subtree));
+        layer = layer
+            .chunks(2)
+            .map(|pair| {
+                let right = pair.get(1).unwrap_or(&zero_subtree);
+                Hash256::from_slice(&hash32_concat(pair[0].as_slice(), right.as_slice()))
+            })
+            .collect();
+        zero_subtree =
+            Hash256::from_slice(&hash32_concat(zero_subtree.as_slice(), zero_subtree.as_slice()));
+        index >>= 1;
+    }
+    branch
+}
+
+fn root_vector(ssz_bytes: &[u8]) -> Vec<Hash256> {
+    ssz_bytes.chunks_exact(32).map(Hash256::from_slice).collect()
+}
+
+impl ProcessSlotWitness {
+    pub fn from_state(state: &BeaconState) -> Self {
+        let index = (state.slot % SLOTS_PER_HISTORICAL_ROOT) as usize;
+        let state_roots: Vec<Hash256> = state.state_roots.iter().copied().collect();
+        let block_roots: Vec<Hash256> = state.block_roots.iter().copied().collect();
+
+        Self {
+            field_roots: field_roots(state),
+            slot: state.slot,
+            latest_block_header: state.latest_block_header.clone(),
+            state_roots_entry: state_roots[index],
+            state_roots_branch: vector_branch(&state_roots, index),
+            block_roots_entry: block_roots[index],
+            block_roots_branch: vector_branch(&block_roots, index),
+        }
+    }
+
+    /// Builds the witness inside the guest from a lazily decoded state, decoding only the fields
+    /// `process_slot` reads and the containers [`lazy_field_roots`] hashes. The roots of
+    /// `state_roots` and `block_roots` are those of their branches, so each is hashed once.
+    pub fn from_lazy_state(lazy_state: &LazyBeaconState) -> Result<Self, DecodeError> {
+        let slot: u64 = lazy_state.decode_field(field_index::SLOT)?;
+        let index = (slot % SLOTS_PER_HISTORICAL_ROOT) as usize;
+        let state_roots = root_vector(lazy_state.field_bytes(field_index::STATE_ROOTS));
+        let block_roots = root_vector(lazy_state.field_bytes(field_index::BLOCK_ROOTS));
+        if state_roots.len() != SLOTS_PER_HISTORICAL_ROOT as usize
+            || block_roots.len() != SLOTS_PER_HISTORICAL_ROOT as usize
+        {
+            return Err(DecodeError::BytesInvalid(
+                "historical root vectors have the wrong length".to_string(),
+            ));
+        }
+
+        let state_roots_branch = vector_branch(&state_roots, index);
+        let block_roots_branch = vector_branch(&block_roots, index);
+        let known_roots = [
+            (
+                field_index::STATE_ROOTS,
+                branch_root(state_roots[index], &state_roots_branch, index as u64),
+            ),
+            (
+                field_index::BLOCK_ROOTS,
+                branch_root(block_roots[index], &block_roots_branch, index as u64),
+            ),
+        ];
+
+        Ok(Self {
+            field_roots: lazy_field_roots(lazy_state, &known_roots)?,
+            slot,
+            latest_block_header: lazy_state.decode_field(field_index::LATEST_BLOCK_HEADER)?,
+            state_roots_entry: state_roots[index],
+            state_roots_branch,
+            block_roots_entry: block_roots[index],
+            block_roots_branch,
+        })
+    }
+
+    /// Verifies the witness and applies `process_slot` to it, returning the pre-state and
+    /// post-state roots, or `None` if the witness does not open the pre-state root.
+    pub fn process_slot(mut self) -> Option<(Hash256, Hash256)> {
+        if self.field_roots.len() != BEACON_STATE_FIELD_COUNT {
+            return None;
+        }
+        let pre_state_root = root_from_field_roots(&self.field_roots);
+        let index = self.slot % SLOTS_PER_HISTORICAL_ROOT;
+
+        let opens_pre_state = self.slot.tree_hash_root() == self.field_roots[field_index::SLOT]
+            && self.latest_block_header.tree_hash_root()
+                == self.field_roots[field_index::LATEST_BLOCK_HEADER]
+            && is_valid_merkle_branch(
+                self.state_roots_entry,
+                &self.state_roots_branch,
+                HISTORICAL_ROOTS_DEPTH,
+                index,
+                self.field_roots[field_index::STATE_ROOTS],
+            )
+            && is_valid_merkle_branch(
+                self.block_roots_entry,
+                &self.block_roots_branch,
+                HISTORICAL_ROOTS_DEPTH,
+                index,
+                self.field_roots[field_index::BLOCK_ROOTS],
+            );
+        if !opens_pre_state {
+            return None;
+        }
+
+        // Cache state root
+        self.field_roots[field_index::STATE_ROOTS] =
+            branch_root(pre_state_root, &self.state_roots_branch, index);
+
+        // Cache latest block header state root
+        if self.latest_block_header.state_root == Hash256::ZERO {
+            self.latest_block_header.state_root = pre_state_root;
+        }
+        let previous_block_root = self.latest_block_header.tree_hash_root();
+        self.field_roots[field_index::LATEST_BLOCK_HEADER] = previous_block_root;
+
+        // Cache block root
+        self.field_roots[field_index::BLOCK_ROOTS] =
+            branch_root(previous_block_root, &self.block_roots_branch, index);
+
+        Some((pre_state_root, root_from_field_roots(&self.field_roots)))
+    }
+}
+
+/// Witness of the pre-state for `process_bls_to_execution_change`: the validator the change
+/// names, with its branch into `validators`, and the `genesis_validators_root` its signature
+/// domain is computed from.
+#[derive(Debug, Encode, Decode)]
+pub struct BlsToExecutionChangeWitness {
+    pub field_roots: Vec<Hash256>,
+    pub genesis_validators_root: Hash256,
+    pub validator_count: u64,
+    pub validator_index: u64,
+    pub validator: Validator,
+    pub validator_branch: Vec<Hash256>,
+}
+
+impl BlsToExecutionChangeWitness {
+    /// Builds the witness of the validator at `validator_index`. A change naming a validator
+    /// beyond the registry is invalid whatever the state, so the witness then opens validator 0,
+    /// or is `None` if the registry is empty.
+    pub fn from_state(state: &BeaconState, validator_index: u64) -> Option<Self> {
+        let validator_count = state.validators.len() as u64;
+        let index = if validator_index < validator_count {
+            validator_index
+        } else {
+            0
+        };
+        let validator = state.validators.get(index as usize)?.clone();
+        let validator_roots: Vec<Hash256> =
+            state.validators.iter().map(TreeHash::tree_hash_root).collect();
+
+        Some(Self {
+            field_roots: field_roots(state),
+            genesis_validators_root: state.genesis_validators_root,
+            validator_count,
+            validator_index: index,
+            validator,
+            validator_branch: list_branch(&validator_roots, index as usize, VALIDATORS_DEPTH),
+        })
+    }
+
+    fn validators_root(&self) -> Hash256 {
+        let list_root = branch_root(
+            self.validator.tree_hash_root(),
+            &self.validator_branch,
+            self.validator_index,
+        );
+        mix_in_length(&list_root, self.validator_count as usize)
+    }
+
+    /// Verifies the witness and applies `process_bls_to_execution_change` to it, returning the
+    /// pre-state and post-state roots, or `None` if the witness does not open the pre-state root
+    /// or opens another validator than the one the change names. An invalid change leaves the
+    /// state untouched, as in the full state transition.
+    pub fn process_bls_to_execution_change(
+        mut self,
+        signed_change: &SignedBLSToExecutionChange,
+    ) -> Option<(Hash256, Hash256)> {
+        if self.field_roots.len() != BEACON_STATE_FIELD_COUNT {
+            return None;
+        }
+        let pre_state_root = root_from_field_roots(&self.field_roots);
+
+        let opens_pre_state = self.validator_index < self.validator_count
+            && self.validator_branch.len() == VALIDATORS_DEPTH
+            && self.genesis_validators_root.tree_hash_root()
+                == self.field_roots[field_index::GENESIS_VALIDATORS_ROOT]
+            && self.validators_root() == self.field_roots[field_index::VALIDATORS];
+        let change_index = signed_change.message.validator_index;
+        if !opens_pre_state
+            || (change_index < self.validator_count && change_index != self.validator_index)
+        {
+            return None;
+        }
+
+        if self.apply_bls_to_execution_change(signed_change) {
+            self.field_roots[field_index::VALIDATORS] = self.validators_root();
+        }
+
+        Some((pre_state_root, root_from_field_roots(&self.field_roots)))
+    }
+
+    /// Checks the change against the witnessed validator and sets its withdrawal credentials.
+    fn apply_bls_to_execution_change(&mut self, signed_change: &SignedBLSToExecutionChange) -> bool {
+        let change = &signed_change.message;
+        if change.validator_index != self.validator_index {
+            return false;
+        }
+
+        let credentials = self.validator.withdrawal_credentials;
+        if credentials[0] != BLS_WITHDRAWAL_PREFIX
+            || credentials[1..] != hash(&change.from_bls_pubkey.as_ssz_bytes())[1..]
+        {
+            return false;
+        }
+
+        // Fork-agnostic domain, computed with the genesis fork version
+        let domain = compute_domain(
+            DOMAIN_BLS_TO_EXECUTION_CHANGE,
+            MAINNET_FORK_VERSIONS[0],
+            self.genesis_validators_root,
+        );
+        let signing_root = compute_signing_root(change.tree_hash_root(), domain);
+        if !signed_change
+            .signature
+            .verify(&change.from_bls_pubkey, signing_root.as_slice())
+            .unwrap_or(false)
+        {
+            return false;
+        }
+
+        let mut credentials = [0; 32];
+        credentials[0] = ETH1_ADDRESS_WITHDRAWAL_PREFIX;
+        credentials[12..].copy_from_slice(change.to_execution_address.as_slice());
+        self.validator.withdrawal_credentials = Hash256::from(credentials);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_hash::merkle_root;
+
+    use super::*;
+
+    #[test]
+    fn list_branch_opens_leaves_of_a_zero_padded_tree() {
+        let leaves: Vec<Hash256> = (1..=5).map(|byte| Hash256::repeat_byte(byte)).collect();
+        let bytes: Vec<u8> = leaves.iter().flat_map(|leaf| leaf.0).collect();
+        let root = merkle_root(&bytes, 16);
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            let branch = list_branch(&leaves, index, 4);
+            assert!(is_valid_merkle_branch(*leaf, &branch, 4, index as u64, root));
+        }
+    }
+}
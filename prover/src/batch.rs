@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use ream_lib::{
+    error::{Error, Result},
+    file::read_file,
+};
+
+use crate::cli::operation::OperationName;
+
+/// Test cases whose operations are applied in order to the pre-state of the first one, in a
+/// single guest run.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Batch {
+    pub name: String,
+    pub cases: Vec<BatchCase>,
+}
+
+/// A consensus-spec-tests case, named by its operation and its directory under `pyspec_tests`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BatchCase {
+    pub operation: OperationName,
+    pub case: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Manifest {
+    batches: Vec<Batch>,
+}
+
+impl Batch {
+    /// Name to record results under: the operation shared by all cases, or `mixed`.
+    pub fn operation_label(&self) -> String {
+        match self.cases.split_first() {
+            Some((first, rest)) if rest.iter().all(|case| case.operation == first.operation) => {
+                first.operation.to_string()
+            }
+            _ => "mixed".to_string(),
+        }
+    }
+}
+
+/// Groups consecutive test cases of `operation_name` into batches of `batch_size`.
+pub fn from_test_cases(
+    operation_name: &OperationName,
+    test_cases: &[String],
+    batch_size: usize,
+) -> Vec<Batch> {
+    test_cases
+        .chunks(batch_size.max(1))
+        .map(|chunk| Batch {
+            name: chunk.join("+"),
+            cases: chunk
+                .iter()
+                .map(|case| BatchCase {
+                    operation: operation_name.clone(),
+                    case: case.clone(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Loads the batches of a YAML manifest:
+///
+/// ```yaml
+/// batches:
+///   - name: two_attestations
+///     cases:
+///       - { operation: attestation, case: one_basic_attestation }
+///       - { operation: attestation, case: previous_epoch }
+/// ```
+pub fn load_manifest(path: &Path) -> Result<Vec<Batch>> {
+    let bytes = read_file(path)?;
+    let manifest: Manifest = serde_yaml::from_slice(&bytes).map_err(|source| Error::Yaml {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    if let Some(batch) = manifest.batches.iter().find(|batch| batch.cases.is_empty()) {
+        return Err(Error::EmptyBatch {
+            path: path.to_path_buf(),
+            name: batch.name.clone(),
+        });
+    }
+
+    Ok(manifest.batches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(name: &str, yaml: &str) -> Result<Vec<Batch>> {
+        let path =
+            std::env::temp_dir().join(format!("batch_manifest_{name}_{}.yaml", std::process::id()));
+        std::fs::write(&path, yaml).unwrap();
+        let batches = load_manifest(&path);
+        std::fs::remove_file(&path).unwrap();
+        batches
+    }
+
+    #[test]
+    fn load_manifest_reads_batches() {
+        let yaml = "batches:\n  - name: two\n    cases:\n      - { operation: attestation, case: a }\n      - { operation: deposit, case: b }\n";
+        let batches = load("two", yaml).unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].cases.len(), 2);
+        assert_eq!(batches[0].operation_label(), "mixed");
+    }
+
+    #[test]
+    fn load_manifest_rejects_empty_batch() {
+        let yaml = "batches:\n  - name: full\n    cases:\n      - { operation: attestation, case: a }\n  - name: empty\n    cases: []\n";
+        match load("empty", yaml) {
+            Err(Error::EmptyBatch { name, .. }) => assert_eq!(name, "empty"),
+            other => panic!("expected EmptyBatch, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use clap::Parser;
+use std::path::PathBuf;
+
+use super::operation::OperationName;
+
+#[derive(Debug, Clone, Parser)]
+pub struct BatchArgs {
+    /// Apply the operations of this many consecutive test cases to the first case's pre-state in
+    /// a single guest run
+    #[clap(long, default_value_t = 1)]
+    pub batch_size: usize,
+
+    /// YAML manifest of batches to run instead of the operation's test cases
+    #[clap(long)]
+    pub batch_manifest: Option<PathBuf>,
+
+    /// Run on the pre-states of this operation's test cases instead, e.g. to measure epoch steps
+    /// from the same states (only for operations without an input file)
+    #[clap(long)]
+    pub states_from: Option<OperationName>,
+}
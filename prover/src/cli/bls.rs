@@ -0,0 +1,38 @@
+use clap::{Parser, ValueEnum};
+use derive_more::Display;
+use ream_lib::{frame::SignatureVerification, meta::BlsSetting};
+
+#[derive(Debug, Clone, Parser)]
+pub struct BlsArgs {
+    /// Signature verification in the guest: always `on`, always `off`, or follow each case's
+    /// `bls_setting` in `meta.yaml` (`spec`)
+    #[clap(long, default_value_t = BlsMode::Spec)]
+    pub bls: BlsMode,
+}
+
+#[derive(ValueEnum, Debug, Clone, Default, Display)]
+#[clap(rename_all = "lowercase")]
+pub enum BlsMode {
+    #[display("on")]
+    On,
+
+    #[display("off")]
+    Off,
+
+    #[default]
+    #[display("spec")]
+    Spec,
+}
+
+impl BlsMode {
+    pub fn to_signature_verification(&self, bls_setting: BlsSetting) -> SignatureVerification {
+        match (self, bls_setting) {
+            (BlsMode::On, _) => SignatureVerification::Required,
+            (BlsMode::Off, _) => SignatureVerification::Ignored,
+            (BlsMode::Spec, BlsSetting::Ignored) => SignatureVerification::Ignored,
+            (BlsMode::Spec, BlsSetting::Required | BlsSetting::Optional) => {
+                SignatureVerification::Required
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use clap::{Parser, ValueEnum};
+use derive_more::Display;
+use ream_lib::frame;
+
+#[derive(Debug, Clone, Parser)]
+pub struct DecodingArgs {
+    /// How the guest decodes the pre-state: `eager`ly as a whole, or `lazy` field by field on
+    /// first access (process_slot only)
+    #[clap(long, default_value_t = Decoding::Eager)]
+    pub decoding: Decoding,
+}
+
+#[derive(ValueEnum, Debug, Clone, Default, Display, PartialEq, Eq)]
+#[clap(rename_all = "lowercase")]
+pub enum Decoding {
+    #[default]
+    #[display("eager")]
+    Eager,
+
+    #[display("lazy")]
+    Lazy,
+}
+
+impl Decoding {
+    pub fn to_frame_decoding(&self) -> frame::Decoding {
+        match self {
+            Decoding::Eager => frame::Decoding::Eager,
+            Decoding::Lazy => frame::Decoding::Lazy,
+        }
+    }
+}
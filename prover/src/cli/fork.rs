@@ -1,5 +1,6 @@
 use clap::{Parser, ValueEnum};
 use derive_more::Display;
+use ream_lib::frame;
 
 #[derive(Debug, Clone, Parser)]
 pub struct ForkArgs {
@@ -7,7 +8,7 @@ pub struct ForkArgs {
     pub fork: Fork,
 }
 
-#[derive(ValueEnum, Debug, Clone, Default, Display)]
+#[derive(ValueEnum, Debug, Clone, Default, Display, PartialEq, Eq)]
 #[clap(rename_all = "lowercase")]
 pub enum Fork {
     #[default]
@@ -17,3 +18,12 @@ pub enum Fork {
     #[display("fulu")]
     Fulu,
 }
+
+impl Fork {
+    pub fn to_frame_fork(&self) -> frame::Fork {
+        match self {
+            Fork::Electra => frame::Fork::Electra,
+            Fork::Fulu => frame::Fork::Fulu,
+        }
+    }
+}
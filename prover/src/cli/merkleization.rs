@@ -0,0 +1,31 @@
+use clap::{Parser, ValueEnum};
+use derive_more::Display;
+use ream_lib::frame;
+
+#[derive(Debug, Clone, Parser)]
+pub struct MerkleizationArgs {
+    /// How the guest merkleizes the post-state: hash the `full` state, or re-hash only changed
+    /// fields on top of pre-state field roots supplied by the host (`incremental`)
+    #[clap(long, default_value_t = Merkleization::Full)]
+    pub merkleization: Merkleization,
+}
+
+#[derive(ValueEnum, Debug, Clone, Default, Display, PartialEq, Eq)]
+#[clap(rename_all = "lowercase")]
+pub enum Merkleization {
+    #[default]
+    #[display("full")]
+    Full,
+
+    #[display("incremental")]
+    Incremental,
+}
+
+impl Merkleization {
+    pub fn to_frame_merkleization(&self) -> frame::Merkleization {
+        match self {
+            Merkleization::Full => frame::Merkleization::Full,
+            Merkleization::Incremental => frame::Merkleization::Incremental,
+        }
+    }
+}
@@ -1,2 +1,10 @@
+pub mod batch;
+pub mod bls;
+pub mod decoding;
 pub mod fork;
+pub mod merkleization;
 pub mod operation;
+pub mod phase;
+pub mod query;
+pub mod state_input;
+pub mod transport;
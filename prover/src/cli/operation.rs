@@ -1,15 +1,18 @@
 use clap::{Parser, ValueEnum};
 use derive_more::Display;
 use ream_lib::input::EpochProcessingType;
+use serde::Deserialize;
 
 #[derive(Debug, Clone, Parser)]
 pub struct OperationArgs {
-    #[clap(long, short)]
-    pub operation_name: OperationName,
+    /// Required unless a subcommand is given
+    #[clap(long, short, required = true)]
+    pub operation_name: Option<OperationName>,
 }
 
-#[derive(ValueEnum, Debug, Clone, Display)]
+#[derive(ValueEnum, Deserialize, Debug, Clone, Display, PartialEq, Eq)]
 #[clap(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
 pub enum OperationName {
     #[display("attestation")]
     Attestation,
@@ -18,6 +21,7 @@ pub enum OperationName {
     #[display("block_header")]
     BlockHeader,
     #[display("bls_to_execution_change")]
+    #[serde(rename = "bls_to_execution_change")]
     BLSToExecutionChange,
     #[display("deposit")]
     Deposit,
@@ -60,8 +64,57 @@ pub enum OperationName {
     ParticipationFlagUpdates,
     #[display("sync_committee_updates")]
     SyncCommitteeUpdates,
+    #[display("process_epoch")]
+    ProcessEpoch,
+    #[display("proposer_lookahead")]
+    ProposerLookahead,
     #[display("process_slot")]
     ProcessSlot,
+    // Upgrade of a previous fork's state to `--fork`
+    #[display("fork")]
+    Fork,
+    // Blocks on both sides of the upgrade to `--fork`
+    #[display("transition")]
+    Transition,
+    // Chains of full blocks
+    #[display("finality")]
+    Finality,
+    #[display("random")]
+    Random,
+    // Reward and penalty deltas
+    #[display("rewards_basic")]
+    RewardsBasic,
+    #[display("rewards_leak")]
+    RewardsLeak,
+    #[display("rewards_random")]
+    RewardsRandom,
+    // Standalone containers, decoded and hashed without a state
+    #[display("ssz_static")]
+    SszStatic,
+    // Index shuffling for a seed and count
+    #[display("shuffle")]
+    Shuffle,
+    // Fork choice steps against a store
+    #[display("fork_choice_get_head")]
+    ForkChoiceGetHead,
+    #[display("fork_choice_on_block")]
+    ForkChoiceOnBlock,
+    #[display("fork_choice_ex_ante")]
+    ForkChoiceExAnte,
+    #[display("fork_choice_reorg")]
+    ForkChoiceReorg,
+    #[display("fork_choice_withholding")]
+    ForkChoiceWithholding,
+    // Light client sync protocol
+    #[display("light_client_sync")]
+    LightClientSync,
+    #[display("light_client_update_ranking")]
+    LightClientUpdateRanking,
+    // Single Merkle proofs into a standalone object
+    #[display("light_client_single_merkle_proof")]
+    LightClientSingleMerkleProof,
+    #[display("merkle_proof")]
+    MerkleProof,
 }
 
 impl OperationName {
@@ -92,7 +145,27 @@ impl OperationName {
             OperationName::HistoricalSummariesUpdate => "".to_string(),
             OperationName::ParticipationFlagUpdates => "".to_string(),
             OperationName::SyncCommitteeUpdates => "".to_string(),
+            OperationName::ProcessEpoch => "".to_string(),
+            OperationName::ProposerLookahead => "".to_string(),
             OperationName::ProcessSlot => "".to_string(),
+            OperationName::Fork => "".to_string(),
+            OperationName::Transition => "".to_string(),
+            OperationName::Finality => "".to_string(),
+            OperationName::Random => "".to_string(),
+            OperationName::RewardsBasic => "".to_string(),
+            OperationName::RewardsLeak => "".to_string(),
+            OperationName::RewardsRandom => "".to_string(),
+            OperationName::SszStatic => "serialized".to_string(),
+            OperationName::Shuffle => "mapping".to_string(),
+            OperationName::ForkChoiceGetHead => "steps".to_string(),
+            OperationName::ForkChoiceOnBlock => "steps".to_string(),
+            OperationName::ForkChoiceExAnte => "steps".to_string(),
+            OperationName::ForkChoiceReorg => "steps".to_string(),
+            OperationName::ForkChoiceWithholding => "steps".to_string(),
+            OperationName::LightClientSync => "steps".to_string(),
+            OperationName::LightClientUpdateRanking => "updates".to_string(),
+            OperationName::LightClientSingleMerkleProof => "object".to_string(),
+            OperationName::MerkleProof => "object".to_string(),
         }
     }
 
@@ -111,14 +184,118 @@ impl OperationName {
             OperationName::RandaoMixesReset |
             OperationName::HistoricalSummariesUpdate |
             OperationName::ParticipationFlagUpdates |
-            OperationName::SyncCommitteeUpdates
+            OperationName::SyncCommitteeUpdates |
+            OperationName::ProcessEpoch |
+            OperationName::ProposerLookahead
         )
     }
 
+    /// Whether the operation was introduced in Fulu and has no Electra spec tests.
+    pub fn is_fulu_only(&self) -> bool {
+        matches!(self, OperationName::ProposerLookahead)
+    }
+
+    pub fn is_fork_upgrade(&self) -> bool {
+        matches!(self, OperationName::Fork)
+    }
+
+    pub fn is_transition(&self) -> bool {
+        matches!(self, OperationName::Transition)
+    }
+
+    /// Whether each test case applies a chain of `blocks_<i>.ssz_snappy` with full state
+    /// transitions.
+    pub fn is_block_sequence(&self) -> bool {
+        matches!(self, OperationName::Finality | OperationName::Random)
+    }
+
+    /// Handler directory of the `rewards` runner, for the rewards deltas operations.
+    pub fn rewards_handler(&self) -> Option<&'static str> {
+        match self {
+            OperationName::RewardsBasic => Some("basic"),
+            OperationName::RewardsLeak => Some("leak"),
+            OperationName::RewardsRandom => Some("random"),
+            _ => None,
+        }
+    }
+
+    /// Handler directory of the `fork_choice` runner, for the fork choice operations.
+    pub fn fork_choice_handler(&self) -> Option<&'static str> {
+        match self {
+            OperationName::ForkChoiceGetHead => Some("get_head"),
+            OperationName::ForkChoiceOnBlock => Some("on_block"),
+            OperationName::ForkChoiceExAnte => Some("ex_ante"),
+            OperationName::ForkChoiceReorg => Some("reorg"),
+            OperationName::ForkChoiceWithholding => Some("withholding"),
+            _ => None,
+        }
+    }
+
+    /// Handler directory of the `light_client` runner, for the light client operations.
+    pub fn light_client_handler(&self) -> Option<&'static str> {
+        match self {
+            OperationName::LightClientSync => Some("sync"),
+            OperationName::LightClientUpdateRanking => Some("update_ranking"),
+            _ => None,
+        }
+    }
+
+    /// Runner and handler directories of the `single_merkle_proof` operations, whose cases are
+    /// grouped by the type of their object.
+    pub fn single_merkle_proof_dir(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            OperationName::LightClientSingleMerkleProof => {
+                Some(("light_client", "single_merkle_proof"))
+            }
+            OperationName::MerkleProof => Some(("merkle_proof", "single_merkle_proof")),
+            _ => None,
+        }
+    }
+
+    /// Whether the pre-states of the operation's test cases belong to the previous fork.
+    pub fn has_previous_fork_pre_state(&self) -> bool {
+        self.is_fork_upgrade() || self.is_transition()
+    }
+
+    /// Whether each test case is an `ssz_static/<Type>/<suite>/<case>` directory without a
+    /// pre-state.
+    pub fn is_ssz_static(&self) -> bool {
+        matches!(self, OperationName::SszStatic)
+    }
+
+    pub fn is_shuffle(&self) -> bool {
+        matches!(self, OperationName::Shuffle)
+    }
+
+    /// Whether the operation's test cases have no pre-state at all.
+    pub fn is_stateless(&self) -> bool {
+        self.is_ssz_static() || self.is_shuffle() || self.single_merkle_proof_dir().is_some()
+    }
+
+    pub fn is_process_epoch(&self) -> bool {
+        matches!(self, OperationName::ProcessEpoch)
+    }
+
+    /// Whether the operation's test cases carry an input file to apply besides the pre-state,
+    /// such as an operation, blocks or steps. Stateless operations have no pre-state to apply
+    /// their object to.
+    pub fn has_input_file(&self) -> bool {
+        !self.is_epoch_processing()
+            && !self.is_process_slot()
+            && !self.is_fork_upgrade()
+            && self.rewards_handler().is_none()
+            && !self.is_stateless()
+    }
+
     pub fn is_process_slot(&self) -> bool {
         matches!(self, OperationName::ProcessSlot)
     }
 
+    /// Whether `--state-input witness` can send a multiproof of the pre-state in its place.
+    pub fn has_witness(&self) -> bool {
+        matches!(self, OperationName::ProcessSlot | OperationName::BLSToExecutionChange)
+    }
+
     pub fn to_epoch_processing_type(&self) -> Option<EpochProcessingType> {
         match self {
             OperationName::JustificationAndFinalization => Some(EpochProcessingType::JustificationAndFinalization),
@@ -135,6 +312,8 @@ impl OperationName {
             OperationName::HistoricalSummariesUpdate => Some(EpochProcessingType::HistoricalSummariesUpdate),
             OperationName::ParticipationFlagUpdates => Some(EpochProcessingType::ParticipationFlagUpdates),
             OperationName::SyncCommitteeUpdates => Some(EpochProcessingType::SyncCommitteeUpdates),
+            OperationName::ProcessEpoch => Some(EpochProcessingType::Full),
+            OperationName::ProposerLookahead => Some(EpochProcessingType::ProposerLookahead),
             _ => None,
         }
     }
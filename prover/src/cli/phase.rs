@@ -0,0 +1,43 @@
+use clap::{Parser, ValueEnum};
+use derive_more::Display;
+use ream_lib::frame;
+
+#[derive(Debug, Clone, Parser)]
+pub struct PhaseArgs {
+    /// Make the guest exit after this phase, without committing a state root
+    #[clap(long, default_value_t = Phase::Merkleize)]
+    pub stop_after: Phase,
+
+    /// Run a no-op instead of the operation on the same pre-states, to measure the fixed cost of
+    /// reading, deserializing and merkleizing them
+    #[clap(long, default_value_t = false)]
+    pub noop: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Default, Display, PartialEq, Eq)]
+#[clap(rename_all = "lowercase")]
+pub enum Phase {
+    #[display("read")]
+    Read,
+
+    #[display("deserialize")]
+    Deserialize,
+
+    #[display("process")]
+    Process,
+
+    #[default]
+    #[display("merkleize")]
+    Merkleize,
+}
+
+impl Phase {
+    pub fn to_frame_phase(&self) -> frame::Phase {
+        match self {
+            Phase::Read => frame::Phase::Read,
+            Phase::Deserialize => frame::Phase::Deserialize,
+            Phase::Process => frame::Phase::Process,
+            Phase::Merkleize => frame::Phase::Merkleize,
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use clap::{Parser, ValueEnum};
+use derive_more::Display;
+use ream_lib::state_query::ValidatorField;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Parser)]
+pub struct QueryArgs {
+    /// `.ssz_snappy` file of the state to query
+    #[clap(long)]
+    pub state: PathBuf,
+
+    /// Comma-separated indices of the validators to query
+    #[clap(long, value_delimiter = ',', required = true)]
+    pub indices: Vec<u64>,
+
+    /// Field to read from each queried validator
+    #[clap(long, default_value_t = Field::Balance)]
+    pub field: Field,
+}
+
+#[derive(ValueEnum, Debug, Clone, Default, Display)]
+#[clap(rename_all = "snake_case")]
+pub enum Field {
+    #[default]
+    #[display("balance")]
+    Balance,
+
+    #[display("effective_balance")]
+    EffectiveBalance,
+
+    #[display("status")]
+    Status,
+}
+
+impl Field {
+    pub fn to_validator_field(&self) -> ValidatorField {
+        match self {
+            Field::Balance => ValidatorField::Balance,
+            Field::EffectiveBalance => ValidatorField::EffectiveBalance,
+            Field::Status => ValidatorField::Status,
+        }
+    }
+}
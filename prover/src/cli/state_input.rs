@@ -0,0 +1,31 @@
+use clap::{Parser, ValueEnum};
+use derive_more::Display;
+use ream_lib::frame;
+
+#[derive(Debug, Clone, Parser)]
+pub struct StateInputArgs {
+    /// What the guest receives as pre-state: the `full` `BeaconState`, or a `witness` multiproof of
+    /// the fields the operation touches (process_slot and bls_to_execution_change only)
+    #[clap(long, default_value_t = StateInput::Full)]
+    pub state_input: StateInput,
+}
+
+#[derive(ValueEnum, Debug, Clone, Default, Display, PartialEq, Eq)]
+#[clap(rename_all = "lowercase")]
+pub enum StateInput {
+    #[default]
+    #[display("full")]
+    Full,
+
+    #[display("witness")]
+    Witness,
+}
+
+impl StateInput {
+    pub fn to_frame_state_input(&self) -> frame::StateInput {
+        match self {
+            StateInput::Full => frame::StateInput::Full,
+            StateInput::Witness => frame::StateInput::Witness,
+        }
+    }
+}
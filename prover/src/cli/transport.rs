@@ -0,0 +1,31 @@
+use clap::{Parser, ValueEnum};
+use derive_more::Display;
+use ream_lib::frame;
+
+#[derive(Debug, Clone, Parser)]
+pub struct TransportArgs {
+    /// How the pre-state is shipped into the guest: `raw` SSZ decompressed on the host, or
+    /// `snappy` bytes decompressed inside the guest
+    #[clap(long, default_value_t = Transport::Raw)]
+    pub transport: Transport,
+}
+
+#[derive(ValueEnum, Debug, Clone, Default, Display)]
+#[clap(rename_all = "lowercase")]
+pub enum Transport {
+    #[default]
+    #[display("raw")]
+    Raw,
+
+    #[display("snappy")]
+    Snappy,
+}
+
+impl Transport {
+    pub fn to_frame_transport(&self) -> frame::Transport {
+        match self {
+            Transport::Raw => frame::Transport::Raw,
+            Transport::Snappy => frame::Transport::Snappy,
+        }
+    }
+}
@@ -1,33 +1,101 @@
 
 use pico_sdk::{client::{DefaultProverClient},init_logger};
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use std::any::Any;
+use std::collections::BTreeSet;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 use tracing::{error, info};
+use ssz::Encode;
 use tree_hash::{Hash256, TreeHash};
 
 use ream_consensus::{
     attestation::Attestation,
+    attestation_data::AttestationData,
     attester_slashing::AttesterSlashing,
+    beacon_block_header::BeaconBlockHeader,
     bls_to_execution_change::SignedBLSToExecutionChange,
+    checkpoint::Checkpoint,
     deposit::Deposit,
+    deposit_data::DepositData,
     electra::{
-        beacon_block::BeaconBlock, beacon_state::BeaconState, execution_payload::ExecutionPayload,
+        beacon_block::{BeaconBlock, SignedBeaconBlock},
+        beacon_block_body::BeaconBlockBody,
+        beacon_state::BeaconState,
+        execution_payload::ExecutionPayload,
     },
+    eth_1_data::Eth1Data,
     proposer_slashing::ProposerSlashing,
     sync_aggregate::SyncAggregate,
+    sync_committee::SyncCommittee,
+    validator::Validator,
     voluntary_exit::SignedVoluntaryExit,
 };
-use ream_lib::{file::ssz_from_file, input::{OperationInput, EpochProcessingType}, ssz::from_ssz_bytes,load_elf};
+use ream_lib::{
+    error::{Error, Result},
+    file::{decode_snappy_bytes, decode_ssz_bytes, decode_ssz_file, read_file, ssz_from_file},
+    frame::{
+        self, encode_batch, encode_input, FrameOptions, InputHeader, Preset, SignatureVerification,
+    },
+    fulu::FuluBeaconState,
+    input::{EpochProcessingType, OperationInput},
+    load_elf,
+    merkle::StateRootCache,
+    meta::load_meta,
+    output::{GuestOutput, InputStage},
+    rewards::{deltas_digest, DELTAS_FILES},
+    fork_choice::{load_steps, Checks, Step},
+    light_client::{fork_version_of_digest, load_fork_schedule, load_sync_steps, SyncStep},
+    merkle_proof::load_proof,
+    state_query::{query_validators, ValidatorField, ValidatorStatus},
+    shuffling::{load_mapping, mapping_digest},
+    ssz_static::{load_root, SszType},
+    witness::{BlsToExecutionChangeWitness, ProcessSlotWitness},
+};
 
+mod batch;
 mod cli;
-use cli::{fork::Fork, operation::OperationName};
+mod results;
+use cli::{
+    batch::BatchArgs, bls::BlsMode, decoding::Decoding, fork::Fork, merkleization::Merkleization,
+    operation::OperationName, phase::Phase, query::QueryArgs, state_input::StateInput,
+    transport::Transport,
+};
+use results::{CaseResult, CaseStatus, ResultsWriter};
+
+
 
+/// Options that shape the guest's input frame, shared by every test case of a run.
+struct GuestOptions {
+    fork: Fork,
+    transport: Transport,
+    bls: BlsMode,
+    merkleization: Merkleization,
+    state_input: StateInput,
+    decoding: Decoding,
+    stop_after: Phase,
+    noop: bool,
+}
 
+/// Arguments of a spec test run, as opposed to the query subcommand.
+struct RunArgs {
+    operation_name: OperationName,
+    options: GuestOptions,
+    batch: BatchArgs,
+    excluded_cases: Vec<String>,
+    compare_specs: bool,
+    compare_recompute: bool,
+    block_prefixes: bool,
+    results_file: Option<PathBuf>,
+}
 
 /// The arguments for the command.
 #[derive(Parser, Debug)]
-#[clap(author, version, about, long_about = None)]
+#[clap(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// Argument for STFs
     #[clap(flatten)]
     fork: cli::fork::ForkArgs,
@@ -35,6 +103,27 @@ struct Args {
     #[clap(flatten)]
     operation: cli::operation::OperationArgs,
 
+    #[clap(flatten)]
+    transport: cli::transport::TransportArgs,
+
+    #[clap(flatten)]
+    bls: cli::bls::BlsArgs,
+
+    #[clap(flatten)]
+    merkleization: cli::merkleization::MerkleizationArgs,
+
+    #[clap(flatten)]
+    state_input: cli::state_input::StateInputArgs,
+
+    #[clap(flatten)]
+    decoding: cli::decoding::DecodingArgs,
+
+    #[clap(flatten)]
+    phase: cli::phase::PhaseArgs,
+
+    #[clap(flatten)]
+    batch: cli::batch::BatchArgs,
+
     /// Verify the correctness of the state root by comparing against consensus-spec-tests' post_state
     #[clap(long, default_value_t = false)]
     compare_specs: bool,
@@ -43,55 +132,208 @@ struct Args {
     #[clap(long, default_value_t = false)]
     compare_recompute: bool,
 
+    /// Also run every prefix of a block chain case to log the cycles of each of its blocks
+    #[clap(long, default_value_t = false)]
+    block_prefixes: bool,
+
     #[clap(long)]
     excluded_cases: Vec<String>,
+
+    /// Append one JSON line per test case (status, cycles, error) to this file
+    #[clap(long)]
+    results_file: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Prove fields of validators against the root of a local state file, instead of running
+    /// spec tests
+    Query(cli::query::QueryArgs),
 }
 
 fn main() {
     setup_log();
-    let elf = load_elf("../app/elf/riscv32im-pico-zkvm-elf");
+    let elf = load_elf("../app/elf/riscv32im-pico-zkvm-elf").unwrap_or_else(|err| {
+        error!("{err}");
+        std::process::exit(1);
+    });
     println!("Loaded elf, size: {} bytes", elf.len());
-    let (fork, operation_name, excluded_cases, compare_specs, compare_recompute) = parse_args();
-    let (base_dir, test_cases) = load_test_cases(&fork, &operation_name);
-
-    for test_case in test_cases {
-        if excluded_cases.contains(&test_case) {
-            info!("Skipping test case: {test_case}");
-            continue;
+    let args = Args::parse();
+    if let Some(Command::Query(query_args)) = &args.command {
+        let options = guest_options(&args);
+        match run_query(&elf, &options, query_args) {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                error!("{err}");
+                std::process::exit(1);
+            }
         }
+    }
+    let RunArgs {
+        operation_name,
+        options,
+        batch: batch_args,
+        excluded_cases,
+        compare_specs,
+        compare_recompute,
+        block_prefixes,
+        results_file,
+    } = parse_args(args);
+    if options.state_input == StateInput::Witness && !operation_name.has_witness() {
+        error!("Error: --state-input witness only supports process_slot and bls_to_execution_change");
+        std::process::exit(1);
+    }
+    if options.decoding == Decoding::Lazy && !operation_name.is_process_slot() {
+        error!("Error: --decoding lazy only supports process_slot");
+        std::process::exit(1);
+    }
+    let phase_isolated = options.noop || options.stop_after != Phase::Merkleize;
+    let full_state = options.state_input == StateInput::Full && options.decoding == Decoding::Eager;
+    if phase_isolated && !full_state {
+        error!("Error: --noop and --stop-after need --state-input full and --decoding eager");
+        std::process::exit(1);
+    }
+    let batched = batch_args.batch_size > 1 || batch_args.batch_manifest.is_some();
+    if batched && !full_state {
+        error!("Error: batches need --state-input full and --decoding eager");
+        std::process::exit(1);
+    }
+    if operation_name.is_fulu_only() && options.fork == Fork::Electra {
+        error!("Error: {operation_name} only exists from Fulu, pass --fork fulu");
+        std::process::exit(1);
+    }
+    if let Some(states_from) = &batch_args.states_from {
+        if operation_name.has_input_file() {
+            error!("Error: --states-from only supports operations without an input file");
+            std::process::exit(1);
+        }
+        if batch_args.batch_manifest.is_some() {
+            error!("Error: --states-from cannot be combined with --batch-manifest");
+            std::process::exit(1);
+        }
+        info!("Running {operation_name} on the pre-states of {states_from}");
+    }
+    // Only a single spec case run on its own pre-state has a post-state to compare against
+    let recompute = compare_recompute
+        || batched
+        || batch_args.states_from.is_some()
+        || operation_name.is_process_epoch();
+    // The host only decodes states of `--fork`, unlike the pre-states of upgrades and transitions
+    let plain_run = full_state
+        && !phase_isolated
+        && options.merkleization == Merkleization::Full
+        && !recompute;
+    let is_rewards = operation_name.rewards_handler().is_some();
+    let stateless = operation_name.is_stateless();
+    let is_fork_choice = operation_name.fork_choice_handler().is_some();
+    let is_light_client = operation_name.light_client_handler().is_some();
+    let own_checks = is_rewards || stateless || is_fork_choice || is_light_client;
+    if (operation_name.has_previous_fork_pre_state() || own_checks) && !plain_run {
+        error!("Error: {operation_name} only supports full, eager and unbatched runs");
+        std::process::exit(1);
+    }
+    // The host decodes Fulu states only to compare roots, which it cannot recompute
+    if options.fork == Fork::Fulu && !plain_run {
+        error!("Error: --fork fulu only supports full, eager and unbatched runs");
+        std::process::exit(1);
+    }
+    let batches = match &batch_args.batch_manifest {
+        Some(path) => batch::load_manifest(path).unwrap_or_else(|err| {
+            error!("{err}");
+            std::process::exit(1);
+        }),
+        None => {
+            let states_operation = batch_args.states_from.as_ref().unwrap_or(&operation_name);
+            let test_cases: Vec<String> = load_test_cases(&options.fork, states_operation)
+                .into_iter()
+                .filter(|test_case| {
+                    let excluded = excluded_cases.contains(test_case);
+                    if excluded {
+                        info!("Skipping test case: {test_case}");
+                    }
+                    !excluded
+                })
+                .collect();
+            batch::from_test_cases(&operation_name, &test_cases, batch_args.batch_size)
+        }
+    };
+    let mut results = ResultsWriter::new(results_file.as_deref()).unwrap_or_else(|err| {
+        error!("Could not open results file: {err}");
+        std::process::exit(1);
+    });
 
-        info!("[{operation_name}] Test case: {test_case}");
+    for batch in batches {
+        let operation = batch.operation_label();
+        let test_case = batch.name;
+        info!("[{operation}] Test case: {test_case}");
 
-        let case_dir = &base_dir.join(&test_case);
-        let input = prepare_input(&case_dir, &operation_name);
-        let pre_state_ssz_bytes: Vec<u8> = ssz_from_file(&case_dir.join("pre.ssz_snappy"));
+        let cases: Vec<(OperationName, PathBuf)> = batch
+            .cases
+            .into_iter()
+            .map(|case| {
+                let states_operation = batch_args.states_from.as_ref().unwrap_or(&case.operation);
+                let case_dir = operation_dir(&options.fork, states_operation).join(&case.case);
+                (case.operation, case_dir)
+            })
+            .collect();
+        let inputs = load_inputs(&cases, &options);
+        let operations = inputs.as_ref().map_or(0, Vec::len);
+        // The prefixes rerun the blocks of the case, so they are kept before running it
+        let prefix_inputs = match &inputs {
+            Ok(inputs) if block_prefixes && operation_name.is_block_sequence() => Some(inputs.clone()),
+            _ => None,
+        };
+        let outcome = inputs.and_then(|inputs| run_case(&elf, &cases, inputs, &options, recompute));
+        let (status, cycles, error) = match outcome {
+            Ok(CaseOutcome::Matches(cycles)) => (CaseStatus::Ok, Some(cycles), None),
+            Ok(CaseOutcome::Mismatch(cycles)) => (
+                CaseStatus::RootMismatch,
+                Some(cycles),
+                Some("state root does not match the expected root".to_string()),
+            ),
+            Ok(CaseOutcome::Rejected(cycles, stage)) => (
+                CaseStatus::InvalidInput,
+                Some(cycles),
+                Some(format!("guest rejected the input at {stage:?}")),
+            ),
+            Ok(CaseOutcome::MalformedOutput(cycles)) => (
+                CaseStatus::MalformedOutput,
+                Some(cycles),
+                Some("guest committed malformed public values".to_string()),
+            ),
+            Ok(CaseOutcome::Stopped(cycles, phase)) => {
+                info!("Guest stopped after {phase:?}");
+                (CaseStatus::Stopped, Some(cycles), None)
+            }
+            Ok(CaseOutcome::GuestPanic(message)) => (CaseStatus::GuestPanic, None, Some(message)),
+            Err(err) => (CaseStatus::LoadError, None, Some(err.to_string())),
+        };
+        // Report the cost per operation when several share the deserialize and merkleize costs
+        let (batch_size, cycles_per_operation) = match operations {
+            0 | 1 => (None, None),
+            len => (Some(len), cycles.map(|cycles| cycles / len as u64)),
+        };
+        if let Some(cycles_per_operation) = cycles_per_operation {
+            info!("Amortized cycles per operation: {cycles_per_operation}");
+        }
+        let result = CaseResult {
+            operation,
+            test_case,
+            status,
+            cycles,
+            batch_size,
+            cycles_per_operation,
+            error,
+        };
+        results.record(result);
 
-        // Setup the executor environment and inject inputs
-        let client = DefaultProverClient::new(&elf);
-        let mut stdin_builder = client.new_stdin_builder();
-        stdin_builder
-            .write(&pre_state_ssz_bytes.len());
-        stdin_builder.write_slice(&pre_state_ssz_bytes);
-        stdin_builder.write(&input);
-            // .unwrap() 
-            // .write_slice(&pre_state_ssz_bytes)
-            // Operation input
-            // .unwrap()
-            // Build the environment
-            // .build()
-            // .unwrap();
+        if let Some(inputs) = prefix_inputs {
+            if let Err(err) = log_block_cycles(&elf, &cases, &inputs, &options) {
+                error!("Could not run the block prefixes: {err}");
+            }
+        }
 
-        //
-        // Prover setup & proving
-        //
-        let (cycles,raw_output) = client.emulate(stdin_builder);
-        let (prefix_bytes, root_bytes) = raw_output.split_at(8);
-        let state_root = Hash256::from_slice(root_bytes);
-
-        println!("Execution complete in {} cycles",cycles);
-        println!("Output size: {} bytes", state_root.len());
-        println!("Output: {:#?}", state_root);
-        assert_state_root_matches_specs(&state_root, &pre_state_ssz_bytes, &case_dir);
         // let publicValues=deserialize::<Vec<u8>>(&output);
         //
         // Proof verification
@@ -113,16 +355,521 @@ fn main() {
 
         // if compare_specs {
         //     info!("Comparing the root against consensus-spec-tests post_state");
-        //     assert_state_root_matches_specs(&new_state_root, &pre_state_ssz_bytes, &case_dir);
+        //     state_root_matches_specs(&new_state_root, &pre_state_ssz_bytes, &case_dir)?;
         // }
 
         // if compare_recompute {
         //     info!("Comparing the root by recomputing on host");
-        //     assert_state_root_matches_recompute(&new_state_root, &pre_state_ssz_bytes, &input);
+        //     state_root_matches_recompute(&new_state_root, &pre_state_ssz_bytes, &input, &case_dir)?;
         // }
 
         info!("----- Cycle Tracker End -----");
     }
+
+    if results.failed() > 0 {
+        error!("{} test case(s) failed", results.failed());
+    }
+}
+
+/// Result of emulating a test case whose inputs could be loaded on the host.
+enum CaseOutcome {
+    Matches(u64),
+    Mismatch(u64),
+    Rejected(u64, InputStage),
+    MalformedOutput(u64),
+    Stopped(u64, frame::Phase),
+    GuestPanic(String),
+}
+
+/// Runs a no-op and then every prefix of the blocks of a block chain case on its pre-state, and
+/// logs the cycles each block adds to the previous prefix. Only the cycles are kept: the
+/// post-state of a strict prefix is not the case's post-state.
+fn log_block_cycles(
+    elf: &[u8],
+    cases: &[(OperationName, PathBuf)],
+    inputs: &[OperationInput],
+    options: &GuestOptions,
+) -> Result<()> {
+    let prefix_cycles = |prefix: Vec<OperationInput>| -> Result<Option<u64>> {
+        Ok(match run_case(elf, cases, prefix, options, false)? {
+            CaseOutcome::Matches(cycles)
+            | CaseOutcome::Mismatch(cycles)
+            | CaseOutcome::Stopped(cycles, _) => Some(cycles),
+            CaseOutcome::Rejected(..)
+            | CaseOutcome::MalformedOutput(_)
+            | CaseOutcome::GuestPanic(_) => None,
+        })
+    };
+
+    // The no-op run holds the cycles every prefix pays to read, decode and merkleize the state
+    let Some(baseline) = prefix_cycles(vec![OperationInput::Noop])? else {
+        error!("The no-op run of the block prefixes failed");
+        return Ok(());
+    };
+    let mut previous = baseline;
+    for len in 1..=inputs.len() {
+        let Some(cycles) = prefix_cycles(inputs[..len].to_vec())? else {
+            error!("Block prefix of length {len} failed, stopping the prefix runs");
+            return Ok(());
+        };
+        info!(
+            "Block {}: {} cycles, {} cumulative",
+            len - 1,
+            cycles.saturating_sub(previous),
+            cycles.saturating_sub(baseline)
+        );
+        previous = cycles;
+    }
+    Ok(())
+}
+
+/// Loads the inputs of every case of a batch, in order, or a single no-op with `--noop`.
+fn load_inputs(
+    cases: &[(OperationName, PathBuf)],
+    options: &GuestOptions,
+) -> Result<Vec<OperationInput>> {
+    if options.noop {
+        return Ok(vec![OperationInput::Noop]);
+    }
+
+    let inputs = cases
+        .iter()
+        .map(|(operation_name, case_dir)| prepare_inputs(case_dir, operation_name))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(inputs.into_iter().flatten().collect())
+}
+
+/// Runs `inputs` on the pre-state of the first of `cases` in the emulator, and checks the
+/// committed root against consensus-spec-tests or, with `recompute`, against the host applying
+/// the same operations.
+fn run_case(
+    elf: &[u8],
+    cases: &[(OperationName, PathBuf)],
+    inputs: Vec<OperationInput>,
+    options: &GuestOptions,
+    recompute: bool,
+) -> Result<CaseOutcome> {
+    let case_dir = cases[0].1.as_path();
+    if let [OperationInput::SszStatic(..)
+    | OperationInput::Shuffle { .. }
+    | OperationInput::MerkleProof { .. }] = inputs.as_slice()
+    {
+        return run_stateless_case(elf, case_dir, inputs, options);
+    }
+    if let [OperationInput::ForkChoiceAnchor(_), ..] = inputs.as_slice() {
+        return run_fork_choice_case(elf, case_dir, inputs, options);
+    }
+    if let [
+        OperationInput::LightClientBootstrap { .. } | OperationInput::RankedLightClientUpdate(_),
+        ..,
+    ] = inputs.as_slice()
+    {
+        return run_light_client_case(elf, case_dir, inputs, options);
+    }
+    if options.fork == Fork::Fulu {
+        return run_fulu_case(elf, case_dir, inputs, options);
+    }
+    // Signature checks follow the first case, whose pre-state the whole batch runs on
+    let signatures = options.bls.to_signature_verification(load_meta(case_dir)?.bls_setting());
+    info!("Signature verification: {signatures:?}");
+    let pre_state_path = case_dir.join("pre.ssz_snappy");
+    let pre_state_snappy_bytes: Vec<u8> = read_file(&pre_state_path)?;
+    let pre_state_ssz_bytes: Vec<u8> = decode_snappy_bytes(&pre_state_snappy_bytes, &pre_state_path)?;
+    let cached_roots = match options.merkleization {
+        Merkleization::Full => Vec::new(),
+        Merkleization::Incremental => {
+            let pre_state: BeaconState = decode_ssz_bytes(&pre_state_ssz_bytes, &pre_state_path)?;
+            StateRootCache::from_state(&pre_state).encode()
+        }
+    };
+    // A witness is generated on the host and always shipped as raw SSZ
+    let (transport, pre_state_bytes) = match (&options.state_input, &options.transport) {
+        (StateInput::Witness, _) => {
+            let pre_state: BeaconState = decode_ssz_bytes(&pre_state_ssz_bytes, &pre_state_path)?;
+            let witness_ssz_bytes = match inputs.as_slice() {
+                [OperationInput::SignedBLSToExecutionChange(ssz_bytes)] => {
+                    let bls_change: SignedBLSToExecutionChange =
+                        decode_ssz_bytes(ssz_bytes, &input_path(case_dir, &cases[0].0))?;
+                    let validator_index = bls_change.message.validator_index;
+                    BlsToExecutionChangeWitness::from_state(&pre_state, validator_index)
+                        .ok_or_else(|| Error::EmptyValidatorRegistry {
+                            path: pre_state_path.clone(),
+                        })?
+                        .as_ssz_bytes()
+                }
+                _ => ProcessSlotWitness::from_state(&pre_state).as_ssz_bytes(),
+            };
+            (frame::Transport::Raw, witness_ssz_bytes)
+        }
+        (StateInput::Full, Transport::Raw) => (frame::Transport::Raw, pre_state_ssz_bytes.clone()),
+        (StateInput::Full, Transport::Snappy) => {
+            (frame::Transport::Snappy, pre_state_snappy_bytes)
+        }
+    };
+    // consensus-spec-tests are loaded from the mainnet preset directory
+    let frame_options = FrameOptions {
+        fork: options.fork.to_frame_fork(),
+        preset: Preset::Mainnet,
+        transport,
+        signatures,
+        merkleization: options.merkleization.to_frame_merkleization(),
+        state_input: options.state_input.to_frame_state_input(),
+        decoding: options.decoding.to_frame_decoding(),
+        stop_after: options.stop_after.to_frame_phase(),
+    };
+    let (header, payload) = encode_batch_or_input(frame_options, inputs.clone());
+
+    let buffers = [
+        header.to_bytes().to_vec(),
+        pre_state_bytes,
+        payload,
+        cached_roots,
+    ];
+    let (cycles, output) = match emulate_output(elf, &buffers) {
+        Ok(emulation) => emulation,
+        Err(outcome) => return Ok(outcome),
+    };
+
+    let state_root = match output {
+        GuestOutput::StateRoot(state_root) => state_root,
+        GuestOutput::StateTransition {
+            supplied_pre_state_root,
+            state_root,
+        } => {
+            // The guest does not tie the supplied roots to the pre-state bytes, so the host does
+            let pre_state: BeaconState = decode_ssz_bytes(&pre_state_ssz_bytes, &pre_state_path)?;
+            if supplied_pre_state_root != pre_state.tree_hash_root() {
+                return Ok(CaseOutcome::Mismatch(cycles));
+            }
+            state_root
+        }
+        GuestOutput::RewardsDeltas(digest) => {
+            return if digest == expected_deltas_digest(case_dir)? {
+                info!("Execution is correct! Rewards deltas match consensus-spec-tests.");
+                Ok(CaseOutcome::Matches(cycles))
+            } else {
+                Ok(CaseOutcome::Mismatch(cycles))
+            };
+        }
+        // Only stateless, fork choice and light client cases and state queries, which never reach
+        // this point, commit the other outputs
+        _ => return Ok(CaseOutcome::MalformedOutput(cycles)),
+    };
+    println!("Output: {:#?}", state_root);
+
+    // A no-op leaves the pre-state untouched, whatever the case's post-state is
+    if options.noop {
+        let pre_state: BeaconState = decode_ssz_bytes(&pre_state_ssz_bytes, &pre_state_path)?;
+        return if state_root == pre_state.tree_hash_root() {
+            Ok(CaseOutcome::Matches(cycles))
+        } else {
+            Ok(CaseOutcome::Mismatch(cycles))
+        };
+    }
+
+    let matches = if recompute {
+        let input_paths = input_paths(cases)?;
+        state_root_matches_recompute(
+            &state_root,
+            &pre_state_ssz_bytes,
+            &pre_state_path,
+            &inputs,
+            &input_paths,
+        )?
+    } else {
+        state_root_matches_specs(&state_root, &pre_state_ssz_bytes, case_dir)?
+    };
+    if matches {
+        Ok(CaseOutcome::Matches(cycles))
+    } else {
+        Ok(CaseOutcome::Mismatch(cycles))
+    }
+}
+
+/// Runs a case on a Fulu pre-state, and checks the committed root against the Fulu post-state, or
+/// against the pre-state when the case has none.
+fn run_fulu_case(
+    elf: &[u8],
+    case_dir: &Path,
+    inputs: Vec<OperationInput>,
+    options: &GuestOptions,
+) -> Result<CaseOutcome> {
+    let frame_options = default_frame_options(options);
+    let pre_state_path = case_dir.join("pre.ssz_snappy");
+    let pre_state_ssz_bytes = ssz_from_file(&pre_state_path)?;
+    let (header, payload) = encode_batch_or_input(frame_options, inputs);
+    let buffers = [header.to_bytes().to_vec(), pre_state_ssz_bytes, payload, Vec::new()];
+    let (cycles, output) = match emulate_output(elf, &buffers) {
+        Ok(emulation) => emulation,
+        Err(outcome) => return Ok(outcome),
+    };
+
+    let GuestOutput::StateRoot(state_root) = output else {
+        return Ok(CaseOutcome::MalformedOutput(cycles));
+    };
+    let post_state_path = case_dir.join("post.ssz_snappy");
+    let expected_path = if post_state_path.exists() {
+        post_state_path
+    } else {
+        pre_state_path
+    };
+    let expected_state: FuluBeaconState = decode_ssz_file(&expected_path)?;
+    if state_root == expected_state.tree_hash_root() {
+        info!("Execution is correct! The root matches the Fulu state of consensus-spec-tests.");
+        Ok(CaseOutcome::Matches(cycles))
+    } else {
+        Ok(CaseOutcome::Mismatch(cycles))
+    }
+}
+
+/// Runs an `ssz_static`, `shuffling` or `single_merkle_proof` case, which has no pre-state, and
+/// checks the committed root against its `roots.yaml`, the digest of its `mapping.yaml` or the
+/// leaf and generalized index of its `proof.yaml`.
+fn run_stateless_case(
+    elf: &[u8],
+    case_dir: &Path,
+    inputs: Vec<OperationInput>,
+    options: &GuestOptions,
+) -> Result<CaseOutcome> {
+    let frame_options = default_frame_options(options);
+    // Single Merkle proof cases send their object in place of a pre-state
+    let (object_bytes, object_root) = match inputs.first() {
+        Some(OperationInput::MerkleProof { ssz_type, .. }) => {
+            let object_path = case_dir.join("object.ssz_snappy");
+            let object_bytes = ssz_from_file(&object_path)?;
+            let object_root = ssz_object_root(*ssz_type, &object_bytes, &object_path)?;
+            (object_bytes, Some(object_root))
+        }
+        _ => (Vec::new(), None),
+    };
+    let (header, payload) = encode_batch_or_input(frame_options, inputs);
+    let buffers = [header.to_bytes().to_vec(), object_bytes, payload, Vec::new()];
+    let (cycles, output) = match emulate_output(elf, &buffers) {
+        Ok(emulation) => emulation,
+        Err(outcome) => return Ok(outcome),
+    };
+
+    let matches = match output {
+        GuestOutput::SszRoot(root) => root == load_root(case_dir)?,
+        GuestOutput::ShuffleMapping(digest) => {
+            digest == mapping_digest(&load_mapping(case_dir)?.mapping)
+        }
+        // The guest only commits after the branch opened the root it computed, which must also
+        // be the root of the object
+        GuestOutput::MerkleProof { root, leaf, gindex } => {
+            let proof = load_proof(case_dir)?;
+            Some(root) == object_root && leaf == proof.leaf && gindex == proof.leaf_index
+        }
+        _ => return Ok(CaseOutcome::MalformedOutput(cycles)),
+    };
+    if matches {
+        info!("Execution is correct! The committed root matches consensus-spec-tests.");
+        Ok(CaseOutcome::Matches(cycles))
+    } else {
+        Ok(CaseOutcome::Mismatch(cycles))
+    }
+}
+
+/// Hash tree root of the `ssz_type` object in `ssz_bytes`, which were loaded from `path`.
+fn ssz_object_root(ssz_type: SszType, ssz_bytes: &[u8], path: &Path) -> Result<Hash256> {
+    fn root<T: ssz::Decode + TreeHash>(ssz_bytes: &[u8], path: &Path) -> Result<Hash256> {
+        Ok(decode_ssz_bytes::<T>(ssz_bytes, path)?.tree_hash_root())
+    }
+
+    match ssz_type {
+        SszType::Attestation => root::<Attestation>(ssz_bytes, path),
+        SszType::AttesterSlashing => root::<AttesterSlashing>(ssz_bytes, path),
+        SszType::BeaconBlock => root::<BeaconBlock>(ssz_bytes, path),
+        SszType::BeaconBlockHeader => root::<BeaconBlockHeader>(ssz_bytes, path),
+        SszType::BeaconState => root::<BeaconState>(ssz_bytes, path),
+        SszType::Deposit => root::<Deposit>(ssz_bytes, path),
+        SszType::ExecutionPayload => root::<ExecutionPayload>(ssz_bytes, path),
+        SszType::ProposerSlashing => root::<ProposerSlashing>(ssz_bytes, path),
+        SszType::SignedBeaconBlock => root::<SignedBeaconBlock>(ssz_bytes, path),
+        SszType::SignedBLSToExecutionChange => root::<SignedBLSToExecutionChange>(ssz_bytes, path),
+        SszType::SignedVoluntaryExit => root::<SignedVoluntaryExit>(ssz_bytes, path),
+        SszType::SyncAggregate => root::<SyncAggregate>(ssz_bytes, path),
+        SszType::AttestationData => root::<AttestationData>(ssz_bytes, path),
+        SszType::Checkpoint => root::<Checkpoint>(ssz_bytes, path),
+        SszType::DepositData => root::<DepositData>(ssz_bytes, path),
+        SszType::Eth1Data => root::<Eth1Data>(ssz_bytes, path),
+        SszType::Fork => root::<ream_consensus::fork::Fork>(ssz_bytes, path),
+        SszType::SyncCommittee => root::<SyncCommittee>(ssz_bytes, path),
+        SszType::Validator => root::<Validator>(ssz_bytes, path),
+        SszType::BeaconBlockBody => root::<BeaconBlockBody>(ssz_bytes, path),
+    }
+}
+
+/// Runs the steps of a `fork_choice` case from its anchor state, and checks the store snapshots
+/// committed at its `checks` steps against `steps.yaml`.
+fn run_fork_choice_case(
+    elf: &[u8],
+    case_dir: &Path,
+    inputs: Vec<OperationInput>,
+    options: &GuestOptions,
+) -> Result<CaseOutcome> {
+    let frame_options = default_frame_options(options);
+    let anchor_state_bytes = ssz_from_file(&case_dir.join("anchor_state.ssz_snappy"))?;
+    let (header, payload) = encode_batch_or_input(frame_options, inputs);
+    let buffers = [header.to_bytes().to_vec(), anchor_state_bytes, payload, Vec::new()];
+    let (cycles, output) = match emulate_output(elf, &buffers) {
+        Ok(emulation) => emulation,
+        Err(outcome) => return Ok(outcome),
+    };
+
+    let GuestOutput::ForkChoice(snapshots) = output else {
+        return Ok(CaseOutcome::MalformedOutput(cycles));
+    };
+    let checks: Vec<Checks> = load_steps(case_dir)?
+        .into_iter()
+        .filter_map(|step| match step {
+            Step::Checks { checks } => Some(checks),
+            _ => None,
+        })
+        .collect();
+    let matches = checks.len() == snapshots.len()
+        && checks
+            .iter()
+            .zip(&snapshots)
+            .all(|(checks, snapshot)| checks.matches(snapshot));
+    if matches {
+        info!("Execution is correct! Every check of steps.yaml matches the store.");
+        Ok(CaseOutcome::Matches(cycles))
+    } else {
+        Ok(CaseOutcome::Mismatch(cycles))
+    }
+}
+
+/// Runs a light client case and checks the committed store headers against the checks of
+/// `steps.yaml`, or that an update ranking case's updates were found in order.
+fn run_light_client_case(
+    elf: &[u8],
+    case_dir: &Path,
+    inputs: Vec<OperationInput>,
+    options: &GuestOptions,
+) -> Result<CaseOutcome> {
+    let frame_options = default_frame_options(options);
+    // Sync cases send their bootstrap in place of a pre-state
+    let bootstrap_bytes = match inputs.first() {
+        Some(OperationInput::LightClientBootstrap { .. }) => {
+            ssz_from_file(&case_dir.join("bootstrap.ssz_snappy"))?
+        }
+        _ => Vec::new(),
+    };
+    let (header, payload) = encode_batch_or_input(frame_options, inputs);
+    let buffers = [header.to_bytes().to_vec(), bootstrap_bytes, payload, Vec::new()];
+    let (cycles, output) = match emulate_output(elf, &buffers) {
+        Ok(emulation) => emulation,
+        Err(outcome) => return Ok(outcome),
+    };
+
+    let matches = match output {
+        GuestOutput::LightClientSync(snapshots) => {
+            let steps = load_sync_steps(case_dir)?;
+            steps.len() == snapshots.len()
+                && steps
+                    .iter()
+                    .zip(&snapshots)
+                    .all(|(step, snapshot)| step.checks().matches(snapshot))
+        }
+        GuestOutput::LightClientUpdateRanking(sorted) => sorted,
+        _ => return Ok(CaseOutcome::MalformedOutput(cycles)),
+    };
+    if matches {
+        info!("Execution is correct! The light client store matches consensus-spec-tests.");
+        Ok(CaseOutcome::Matches(cycles))
+    } else {
+        Ok(CaseOutcome::Mismatch(cycles))
+    }
+}
+
+/// Frame options of the runs that only support the default options and bring their own state,
+/// if any.
+fn default_frame_options(options: &GuestOptions) -> FrameOptions {
+    FrameOptions {
+        fork: options.fork.to_frame_fork(),
+        preset: Preset::Mainnet,
+        transport: frame::Transport::Raw,
+        signatures: SignatureVerification::Required,
+        merkleization: frame::Merkleization::Full,
+        state_input: frame::StateInput::Full,
+        decoding: frame::Decoding::Eager,
+        stop_after: options.stop_after.to_frame_phase(),
+    }
+}
+
+/// Emulates the guest on the given stdin buffers, returning its cycles and public values or the
+/// message of a guest panic.
+fn emulate(elf: &[u8], buffers: &[Vec<u8>]) -> std::result::Result<(u64, Vec<u8>), String> {
+    // The emulator panics when the guest panics, so contain it to this case instead of letting it
+    // unwind through the whole run.
+    let emulation = panic::catch_unwind(AssertUnwindSafe(|| {
+        // Setup the executor environment and inject inputs
+        let client = DefaultProverClient::new(elf);
+        let mut stdin_builder = client.new_stdin_builder();
+        for buffer in buffers {
+            stdin_builder.write_slice(buffer);
+        }
+
+        //
+        // Prover setup & proving
+        //
+        client.emulate(stdin_builder)
+    }));
+    let (cycles, raw_output) = emulation.map_err(panic_message)?;
+    println!("Execution complete in {} cycles",cycles);
+    println!("Output size: {} bytes", raw_output.len());
+
+    Ok((cycles, raw_output))
+}
+
+/// Emulates the guest and decodes its public values. A guest panic, a stop after an isolated
+/// phase, a rejected input and public values that do not decode end the case with their outcome
+/// instead, so runners only match the outputs they expect.
+fn emulate_output(
+    elf: &[u8],
+    buffers: &[Vec<u8>],
+) -> std::result::Result<(u64, GuestOutput), CaseOutcome> {
+    let (cycles, raw_output) = emulate(elf, buffers).map_err(CaseOutcome::GuestPanic)?;
+    match bincode::deserialize::<GuestOutput>(&raw_output) {
+        Ok(GuestOutput::Stopped(phase)) => Err(CaseOutcome::Stopped(cycles, phase)),
+        Ok(GuestOutput::InvalidInput(stage)) => Err(CaseOutcome::Rejected(cycles, stage)),
+        Ok(output) => Ok((cycles, output)),
+        Err(_) => Err(CaseOutcome::MalformedOutput(cycles)),
+    }
+}
+
+/// Digest of the delta files of a `rewards` case, as committed by the guest.
+fn expected_deltas_digest(case_dir: &Path) -> Result<Hash256> {
+    let encodings = DELTAS_FILES
+        .iter()
+        .map(|file| ssz_from_file(&case_dir.join(file)))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(deltas_digest(&encodings))
+}
+
+/// Encodes a single input as itself and several as a batch.
+fn encode_batch_or_input(
+    options: FrameOptions,
+    mut inputs: Vec<OperationInput>,
+) -> (InputHeader, Vec<u8>) {
+    match inputs.len() {
+        1 => encode_input(options, inputs.remove(0)),
+        _ => encode_batch(options, inputs),
+    }
+}
+
+/// Extracts the message of a panic caught by [`panic::catch_unwind`].
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "guest panicked with a non-string payload".to_string()
+    }
 }
 
 fn setup_log() {
@@ -138,56 +885,386 @@ fn setup_log() {
         .init();
 }
 
-fn parse_args() -> (Fork, OperationName, Vec<String>, bool, bool) {
-    let args = Args::parse();
+fn guest_options(args: &Args) -> GuestOptions {
+    GuestOptions {
+        fork: args.fork.fork.clone(),
+        transport: args.transport.transport.clone(),
+        bls: args.bls.bls.clone(),
+        merkleization: args.merkleization.merkleization.clone(),
+        state_input: args.state_input.state_input.clone(),
+        decoding: args.decoding.decoding.clone(),
+        stop_after: args.phase.stop_after.clone(),
+        noop: args.phase.noop,
+    }
+}
+
+fn parse_args(args: Args) -> RunArgs {
+    let options = guest_options(&args);
+    // clap only lets a missing operation name through together with a subcommand
+    let operation_name = args.operation.operation_name.expect("--operation-name is required");
+
+    RunArgs {
+        operation_name,
+        options,
+        batch: args.batch,
+        excluded_cases: args.excluded_cases,
+        compare_specs: args.compare_specs,
+        compare_recompute: args.compare_recompute,
+        block_prefixes: args.block_prefixes,
+        results_file: args.results_file,
+    }
+}
+
+/// Runs a state query against a local state file, and checks the committed state root and values
+/// against the ones the host reads from the same state.
+fn run_query(elf: &[u8], options: &GuestOptions, query_args: &QueryArgs) -> Result<bool> {
+    let state_bytes = ssz_from_file(&query_args.state)?;
+    let state: BeaconState = decode_ssz_bytes(&state_bytes, &query_args.state)?;
+    let field = query_args.field.to_validator_field();
+    let input = OperationInput::StateQuery {
+        field,
+        indices: query_args.indices.clone(),
+    };
+    let (header, payload) = encode_input(default_frame_options(options), input);
+    let buffers = [header.to_bytes().to_vec(), state_bytes, payload, Vec::new()];
+    let answer = match emulate_output(elf, &buffers) {
+        Ok((_cycles, GuestOutput::StateQuery(answer))) => answer,
+        Err(CaseOutcome::GuestPanic(message)) => {
+            error!("Error: the guest panicked: {message}");
+            return Ok(false);
+        }
+        Err(CaseOutcome::Rejected(_, stage)) => {
+            error!("Error: the guest rejected the query at {stage:?}");
+            return Ok(false);
+        }
+        Err(CaseOutcome::Stopped(_, phase)) => {
+            error!("Error: the guest stopped after {phase:?} without answering the query");
+            return Ok(false);
+        }
+        Ok(_) | Err(_) => {
+            error!("Error: the guest committed a malformed output");
+            return Ok(false);
+        }
+    };
+    println!("State root: {}", answer.state_root);
+    for (index, value) in answer.indices.iter().zip(&answer.values) {
+        match field {
+            ValidatorField::Status => {
+                let status = ValidatorStatus::from_u8(*value as u8);
+                println!("Validator {index}: {:?}", status);
+            }
+            ValidatorField::Balance | ValidatorField::EffectiveBalance => {
+                println!("Validator {index}: {value} Gwei");
+            }
+        }
+    }
+
+    let matches = answer.state_root == state.tree_hash_root()
+        && answer.field == field
+        && answer.indices == query_args.indices
+        && query_validators(&state, field, &query_args.indices).as_ref() == Some(&answer.values);
+    if matches {
+        info!("Query is correct! The committed root and values match the state file.");
+    } else {
+        error!("Error: the committed root or values do not match the state file");
+    }
+    Ok(matches)
+}
+
+/// Loads the inputs of a test case: its single operation, the blocks of a block sequence case,
+/// the anchor block and steps of a fork choice case, the steps or updates of a light client case,
+/// or the blocks of a transition case with the upgrade between the pre-fork and post-fork ones.
+fn prepare_inputs(case_dir: &Path, operation_name: &OperationName) -> Result<Vec<OperationInput>> {
+    if operation_name.fork_choice_handler().is_some() {
+        return load_fork_choice_inputs(case_dir);
+    }
+    if operation_name.light_client_handler().is_some() {
+        return load_light_client_inputs(case_dir, operation_name);
+    }
+    if operation_name.is_block_sequence() {
+        let blocks = load_blocks(case_dir, load_meta(case_dir)?.blocks_count())?;
+        return Ok(blocks.into_iter().map(OperationInput::SignedBeaconBlock).collect());
+    }
+    if !operation_name.is_transition() {
+        return Ok(vec![prepare_input(case_dir, operation_name)?]);
+    }
+
+    let meta = load_meta(case_dir)?;
+    let fork_epoch = meta.fork_epoch().ok_or_else(|| Error::MissingMetaField {
+        path: case_dir.join("meta.yaml"),
+        field: "fork_epoch",
+    })?;
+    let mut pre_fork_blocks = load_blocks(case_dir, meta.blocks_count())?;
+    // Blocks up to `fork_block` are still of the previous fork
+    let pre_fork_count = meta.fork_block().map_or(0, |fork_block| fork_block + 1);
+    let post_fork_blocks = pre_fork_blocks.split_off(pre_fork_count.min(pre_fork_blocks.len()));
+
+    let inputs = pre_fork_blocks
+        .into_iter()
+        .map(OperationInput::PreForkSignedBeaconBlock)
+        .chain(std::iter::once(OperationInput::UpgradeAtEpoch(fork_epoch)))
+        .chain(post_fork_blocks.into_iter().map(OperationInput::SignedBeaconBlock))
+        .collect();
+
+    Ok(inputs)
+}
+
+/// Turns `anchor_block.ssz_snappy` and `steps.yaml` into the inputs of a fork choice case.
+fn load_fork_choice_inputs(case_dir: &Path) -> Result<Vec<OperationInput>> {
+    let ssz_file = |name: &str| ssz_from_file(&case_dir.join(format!("{name}.ssz_snappy")));
+
+    let steps = load_steps(case_dir)?;
+    // A case is not validated if the store would skip some of its inputs or checks
+    let unsupported_keys: BTreeSet<&str> = steps.iter().flat_map(Step::unsupported_keys).collect();
+    if !unsupported_keys.is_empty() {
+        return Err(Error::UnsupportedStepKeys {
+            path: case_dir.join("steps.yaml"),
+            keys: unsupported_keys.into_iter().map(str::to_string).collect(),
+        });
+    }
+
+    let mut inputs = vec![OperationInput::ForkChoiceAnchor(ssz_file("anchor_block")?)];
+    for step in steps {
+        let input = match step {
+            Step::Tick { tick } => OperationInput::OnTick(tick),
+            Step::Block { block, valid, .. } => OperationInput::OnBlock {
+                block: ssz_file(&block)?,
+                valid,
+            },
+            Step::Attestation { attestation, valid } => OperationInput::OnAttestation {
+                attestation: ssz_file(&attestation)?,
+                valid,
+            },
+            Step::AttesterSlashing {
+                attester_slashing,
+                valid,
+            } => OperationInput::OnAttesterSlashing {
+                attester_slashing: ssz_file(&attester_slashing)?,
+                valid,
+            },
+            Step::Checks { .. } => OperationInput::ForkChoiceChecks,
+        };
+        inputs.push(input);
+    }
+
+    Ok(inputs)
+}
+
+/// Turns `meta.yaml` and `steps.yaml` of a light client sync case into a bootstrap and its steps,
+/// or reads the updates of an update ranking case.
+fn load_light_client_inputs(
+    case_dir: &Path,
+    operation_name: &OperationName,
+) -> Result<Vec<OperationInput>> {
+    let meta = load_meta(case_dir)?;
+    let ssz_file = |name: &str| ssz_from_file(&case_dir.join(format!("{name}.ssz_snappy")));
+    if *operation_name == OperationName::LightClientUpdateRanking {
+        return (0..meta.updates_count())
+            .map(|index| ssz_file(&format!("updates_{index}")))
+            .map(|update| update.map(OperationInput::RankedLightClientUpdate))
+            .collect();
+    }
+
+    let meta_path = case_dir.join("meta.yaml");
+    let missing = |field| Error::MissingMetaField {
+        path: meta_path.clone(),
+        field,
+    };
+    let genesis_validators_root = meta
+        .genesis_validators_root()
+        .ok_or_else(|| missing("genesis_validators_root"))?;
+    let trusted_block_root = meta
+        .trusted_block_root()
+        .ok_or_else(|| missing("trusted_block_root"))?;
+    // Without a `config.yaml`, every update is signed with the version of the store's fork
+    let fork_schedule = match load_fork_schedule(case_dir)? {
+        Some(fork_schedule) => fork_schedule,
+        None => {
+            let store_fork_digest =
+                meta.store_fork_digest().ok_or_else(|| missing("store_fork_digest"))?;
+            let fork_version = fork_version_of_digest(store_fork_digest, genesis_validators_root)
+                .ok_or_else(|| Error::UnknownForkDigest {
+                    path: meta_path.clone(),
+                    fork_digest: store_fork_digest.to_string(),
+                })?;
+            vec![(0, fork_version)]
+        }
+    };
+
+    let mut inputs = vec![OperationInput::LightClientBootstrap {
+        trusted_block_root,
+        genesis_validators_root,
+        fork_schedule,
+    }];
+    for step in load_sync_steps(case_dir)? {
+        let input = match step {
+            SyncStep::ProcessUpdate {
+                update,
+                current_slot,
+                ..
+            } => OperationInput::LightClientUpdate {
+                update: ssz_file(&update)?,
+                current_slot,
+            },
+            SyncStep::ForceUpdate { current_slot, .. } => {
+                OperationInput::LightClientForceUpdate(current_slot)
+            }
+        };
+        inputs.push(input);
+    }
+
+    Ok(inputs)
+}
+
+/// Reads the SSZ bytes of `blocks_0.ssz_snappy` to `blocks_<count - 1>.ssz_snappy`.
+fn load_blocks(case_dir: &Path, count: usize) -> Result<Vec<Vec<u8>>> {
+    (0..count)
+        .map(|index| ssz_from_file(&block_path(case_dir, index)))
+        .collect()
+}
+
+fn block_path(case_dir: &Path, index: usize) -> PathBuf {
+    case_dir.join(format!("blocks_{index}.ssz_snappy"))
+}
 
-    (
-        args.fork.fork,
-        args.operation.operation_name,
-        args.excluded_cases,
-        args.compare_specs,
-        args.compare_recompute,
-    )
+/// Path of the operation file of a case with a single operation.
+fn input_path(case_dir: &Path, operation_name: &OperationName) -> PathBuf {
+    case_dir.join(format!("{}.ssz_snappy", operation_name.to_input_name()))
 }
 
-fn prepare_input(case_dir: &PathBuf, operation_name: &OperationName) -> OperationInput {
-    if operation_name.is_epoch_processing() {
+/// The file each input of `cases` was read from, in the order of [`load_inputs`], for the decode
+/// errors of the host's recomputation. Inputs without a file point at their case directory.
+fn input_paths(cases: &[(OperationName, PathBuf)]) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for (operation_name, case_dir) in cases {
+        if operation_name.is_block_sequence() {
+            let count = load_meta(case_dir)?.blocks_count();
+            paths.extend((0..count).map(|index| block_path(case_dir, index)));
+        } else if operation_name.has_input_file() {
+            paths.push(input_path(case_dir, operation_name));
+        } else {
+            paths.push(case_dir.clone());
+        }
+    }
+
+    Ok(paths)
+}
+
+fn prepare_input(case_dir: &Path, operation_name: &OperationName) -> Result<OperationInput> {
+    let input = if operation_name.is_epoch_processing() {
         // For epoch processing, we don't need input files, just the processing type
         OperationInput::EpochProcessing(operation_name.to_epoch_processing_type().unwrap())
     } else if operation_name.is_process_slot() {
         // For process_slot, we don't need input files
         OperationInput::ProcessSlot
+    } else if operation_name.is_fork_upgrade() {
+        // The target fork is carried by the frame header
+        OperationInput::UpgradeToFork
+    } else if operation_name.rewards_handler().is_some() {
+        // The deltas are computed from the pre-state alone
+        OperationInput::RewardsDeltas
+    } else if operation_name.is_ssz_static() {
+        // Cases live in ssz_static/<Type>/<suite>/<case>
+        let type_name = case_dir
+            .parent()
+            .and_then(Path::parent)
+            .and_then(Path::file_name)
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let ssz_type = SszType::from_name(type_name).ok_or_else(|| Error::UnknownSszType {
+            path: case_dir.to_path_buf(),
+            name: type_name.to_string(),
+        })?;
+        OperationInput::SszStatic(ssz_type, ssz_from_file(&case_dir.join("serialized.ssz_snappy"))?)
+    } else if operation_name.is_shuffle() {
+        // The expected mapping is only read back when checking the guest's digest
+        let mapping = load_mapping(case_dir)?;
+        OperationInput::Shuffle {
+            seed: mapping.seed,
+            count: mapping.count,
+        }
+    } else if operation_name.single_merkle_proof_dir().is_some() {
+        // Cases live in <handler>/<Type>/<case>; the object is sent by `run_stateless_case`
+        let type_name = case_dir
+            .parent()
+            .and_then(Path::file_name)
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let ssz_type = SszType::from_name(type_name).ok_or_else(|| Error::UnknownSszType {
+            path: case_dir.to_path_buf(),
+            name: type_name.to_string(),
+        })?;
+        let proof = load_proof(case_dir)?;
+        OperationInput::MerkleProof {
+            ssz_type,
+            leaf: proof.leaf,
+            gindex: proof.leaf_index,
+            branch: proof.branch,
+        }
     } else {
-        let input_path = &case_dir.join(format!("{}.ssz_snappy", operation_name.to_input_name()));
+        let input_path = &input_path(case_dir, operation_name);
 
         match operation_name {
-            OperationName::Attestation => OperationInput::Attestation(ssz_from_file(input_path)),
+            OperationName::Attestation => OperationInput::Attestation(ssz_from_file(input_path)?),
             OperationName::AttesterSlashing => {
-                OperationInput::AttesterSlashing(ssz_from_file(input_path))
+                OperationInput::AttesterSlashing(ssz_from_file(input_path)?)
             }
-            OperationName::BlockHeader => OperationInput::BeaconBlock(ssz_from_file(input_path)),
+            OperationName::BlockHeader => OperationInput::BeaconBlock(ssz_from_file(input_path)?),
             OperationName::BLSToExecutionChange => {
-                OperationInput::SignedBLSToExecutionChange(ssz_from_file(input_path))
+                OperationInput::SignedBLSToExecutionChange(ssz_from_file(input_path)?)
             }
-            OperationName::Deposit => OperationInput::Deposit(ssz_from_file(input_path)),
+            OperationName::Deposit => OperationInput::Deposit(ssz_from_file(input_path)?),
             OperationName::ExecutionPayload => {
-                OperationInput::BeaconBlockBody(ssz_from_file(input_path))
+                OperationInput::BeaconBlockBody(ssz_from_file(input_path)?)
             }
             OperationName::ProposerSlashing => {
-                OperationInput::ProposerSlashing(ssz_from_file(input_path))
+                OperationInput::ProposerSlashing(ssz_from_file(input_path)?)
             }
-            OperationName::SyncAggregate => OperationInput::SyncAggregate(ssz_from_file(input_path)),
+            OperationName::SyncAggregate => OperationInput::SyncAggregate(ssz_from_file(input_path)?),
             OperationName::VoluntaryExit => {
-                OperationInput::SignedVoluntaryExit(ssz_from_file(input_path))
+                OperationInput::SignedVoluntaryExit(ssz_from_file(input_path)?)
             }
-            OperationName::Withdrawals => OperationInput::ExecutionPayload(ssz_from_file(input_path)),
-            // Epoch processing and process_slot operations are handled above
-            _ => unreachable!("Epoch processing and process_slot operations should be handled above"),
+            OperationName::Withdrawals => OperationInput::ExecutionPayload(ssz_from_file(input_path)?),
+            // Epoch processing, process_slot and fork operations are handled above
+            _ => unreachable!("Operations without input files should be handled above"),
         }
+    };
+
+    Ok(input)
+}
+
+fn load_test_cases(fork: &Fork, operation_name: &OperationName) -> Vec<String> {
+    let dir = operation_dir(fork, operation_name);
+    if operation_name.single_merkle_proof_dir().is_some() {
+        // single_merkle_proof cases are named <Type>/<case>, for the types the guest can decode
+        return SszType::ALL
+            .iter()
+            .flat_map(|ssz_type| {
+                ream_lib::file::get_test_cases(&dir.join(ssz_type.name()))
+                    .into_iter()
+                    .map(|case| format!("{}/{case}", ssz_type.name()))
+            })
+            .collect();
+    }
+    if !operation_name.is_ssz_static() {
+        return ream_lib::file::get_test_cases(&dir);
     }
+
+    // ssz_static cases are named <Type>/<suite>/<case>, for the types the guest can decode
+    let mut test_cases = Vec::new();
+    for ssz_type in SszType::ALL {
+        let type_dir = dir.join(ssz_type.name());
+        for suite in ream_lib::file::get_test_cases(&type_dir) {
+            for case in ream_lib::file::get_test_cases(&type_dir.join(&suite)) {
+                test_cases.push(format!("{}/{suite}/{case}", ssz_type.name()));
+            }
+        }
+    }
+    test_cases
 }
 
-fn load_test_cases(fork: &Fork, operation_name: &OperationName) -> (PathBuf, Vec<String>) {
+/// Directory holding the consensus-spec-tests cases of `operation_name`.
+fn operation_dir(fork: &Fork, operation_name: &OperationName) -> PathBuf {
     // These assets are from consensus-specs repo.
     let test_case_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .join("mainnet")
@@ -199,13 +1276,75 @@ fn load_test_cases(fork: &Fork, operation_name: &OperationName) -> (PathBuf, Vec
         std::process::exit(1);
     }
 
-    let base_dir = if operation_name.is_epoch_processing() {
+    if operation_name.is_ssz_static() {
+        // ssz_static tests are grouped by type and suite, without a pyspec_tests directory
+        test_case_dir.join(format!("{}", fork)).join("ssz_static")
+    } else if let Some((runner, handler)) = operation_name.single_merkle_proof_dir() {
+        // Single Merkle proof tests are grouped by object type, without a pyspec_tests directory
+        test_case_dir.join(format!("{}", fork)).join(runner).join(handler)
+    } else if let Some(handler) = operation_name.light_client_handler() {
+        // Light client tests are in light_client/<handler> directory
+        test_case_dir
+            .join(format!("{}", fork))
+            .join("light_client")
+            .join(handler)
+            .join("pyspec_tests")
+    } else if let Some(handler) = operation_name.fork_choice_handler() {
+        // Fork choice tests are in fork_choice/<handler> directory
+        test_case_dir
+            .join(format!("{}", fork))
+            .join("fork_choice")
+            .join(handler)
+            .join("pyspec_tests")
+    } else if operation_name.is_shuffle() {
+        // Shuffling tests only exist for phase0; the shuffle is unchanged in later forks
+        test_case_dir
+            .join("phase0")
+            .join("shuffling")
+            .join("core")
+            .join("shuffle")
+    } else if operation_name.is_process_epoch() {
+        // process_epoch has no tests of its own; its first step starts from the same states
+        test_case_dir
+            .join(format!("{}", fork))
+            .join("epoch_processing")
+            .join("justification_and_finalization")
+            .join("pyspec_tests")
+    } else if operation_name.is_epoch_processing() {
         // Epoch processing tests are in epoch_processing directory
         test_case_dir
             .join(format!("{}", fork))
             .join("epoch_processing")
             .join(format!("{}", operation_name))
             .join("pyspec_tests")
+    } else if let Some(handler) = operation_name.rewards_handler() {
+        // Rewards tests are in rewards/<handler> directory
+        test_case_dir
+            .join(format!("{}", fork))
+            .join("rewards")
+            .join(handler)
+            .join("pyspec_tests")
+    } else if operation_name.is_block_sequence() {
+        // finality and random tests are in <name>/<name> directory
+        test_case_dir
+            .join(format!("{}", fork))
+            .join(format!("{}", operation_name))
+            .join(format!("{}", operation_name))
+            .join("pyspec_tests")
+    } else if operation_name.is_transition() {
+        // Transition tests are in transition/core directory of the target fork
+        test_case_dir
+            .join(format!("{}", fork))
+            .join("transition")
+            .join("core")
+            .join("pyspec_tests")
+    } else if operation_name.is_fork_upgrade() {
+        // Fork upgrade tests are in fork/fork directory of the target fork
+        test_case_dir
+            .join(format!("{}", fork))
+            .join("fork")
+            .join("fork")
+            .join("pyspec_tests")
     } else if operation_name.is_process_slot() {
         // Process slot tests are in sanity/slots directory
         test_case_dir
@@ -220,22 +1359,18 @@ fn load_test_cases(fork: &Fork, operation_name: &OperationName) -> (PathBuf, Vec
             .join("operations")
             .join(format!("{}", operation_name))
             .join("pyspec_tests")
-    };
-
-    let test_cases = ream_lib::file::get_test_cases(&base_dir);
-
-    (base_dir, test_cases)
+    }
 }
 
-fn assert_state_root_matches_specs(
+fn state_root_matches_specs(
     new_state_root: &Hash256,
     pre_state_ssz_bytes: &[u8],
-    case_dir: &PathBuf,
-) {
+    case_dir: &Path,
+) -> Result<bool> {
+    let post_state_path = case_dir.join("post.ssz_snappy");
     let post_state_opt: Option<BeaconState> = {
-        if case_dir.join("post.ssz_snappy").exists() {
-            let ssz_bytes: Vec<u8> = ssz_from_file(&case_dir.join("post.ssz_snappy"));
-            Some(from_ssz_bytes(&ssz_bytes).unwrap())
+        if post_state_path.exists() {
+            Some(decode_ssz_file(&post_state_path)?)
         } else {
             None
         }
@@ -245,66 +1380,97 @@ fn assert_state_root_matches_specs(
         // If the specs provide post_state, compare the computed root against post_state's root
         Some(post_state) => {
             info!("post_state provided. The state root should be mutated.");
-            assert_eq!(*new_state_root, post_state.tree_hash_root());
-            info!("Execution is correct! State mutated and the roots match.");
+            let matches = *new_state_root == post_state.tree_hash_root();
+            if matches {
+                info!("Execution is correct! State mutated and the roots match.");
+            }
+            Ok(matches)
         }
         // If the specs does not contain a post_state, compare the computed root against pre_state's root
         None => {
             info!("post_state not provided. The state root should not be mutated.");
-            let pre_state: BeaconState = from_ssz_bytes(&pre_state_ssz_bytes).unwrap();
-            assert_eq!(*new_state_root, pre_state.tree_hash_root());
-            info!("Execution is correct! State should not be mutated and the roots match.");
+            let pre_state: BeaconState =
+                decode_ssz_bytes(pre_state_ssz_bytes, &case_dir.join("pre.ssz_snappy"))?;
+            let matches = *new_state_root == pre_state.tree_hash_root();
+            if matches {
+                info!("Execution is correct! State should not be mutated and the roots match.");
+            }
+            Ok(matches)
         }
     }
 }
 
-fn assert_state_root_matches_recompute(
+fn state_root_matches_recompute(
     new_state_root: &Hash256,
     pre_state_ssz_bytes: &[u8],
-    input: &OperationInput,
-) {
-    let mut state: BeaconState = from_ssz_bytes(&pre_state_ssz_bytes).unwrap();
+    pre_state_path: &Path,
+    inputs: &[OperationInput],
+    input_paths: &[PathBuf],
+) -> Result<bool> {
+    let mut state: BeaconState = decode_ssz_bytes(pre_state_ssz_bytes, pre_state_path)?;
+
+    for (input, input_path) in inputs.iter().zip(input_paths) {
+        recompute_operation(&mut state, input, input_path)?;
+    }
+
+    let recomputed_state_root = state.tree_hash_root();
+
+    println!("recomputed_state_root: {}", recomputed_state_root);
+    println!("new_state_root: {}", new_state_root);
+
+    let matches = *new_state_root == recomputed_state_root;
+    if matches {
+        info!("Execution is correct! State roots match host's recomputed state root.");
+    }
+    Ok(matches)
+}
 
+/// Applies `input`, read from `input_path`, to `state` on the host, mirroring the guest.
+fn recompute_operation(
+    state: &mut BeaconState,
+    input: &OperationInput,
+    input_path: &Path,
+) -> Result<()> {
     match input {
         OperationInput::Attestation(ssz_bytes) => {
-            let attestation: Attestation = from_ssz_bytes(&ssz_bytes).unwrap();
+            let attestation: Attestation = decode_ssz_bytes(ssz_bytes, input_path)?;
             let _ = state.process_attestation(&attestation);
         }
         OperationInput::AttesterSlashing(ssz_bytes) => {
-            let attester_slashing: AttesterSlashing = from_ssz_bytes(&ssz_bytes).unwrap();
+            let attester_slashing: AttesterSlashing = decode_ssz_bytes(ssz_bytes, input_path)?;
             let _ = state.process_attester_slashing(&attester_slashing);
         }
         OperationInput::BeaconBlock(ssz_bytes) => {
-            let block: BeaconBlock = from_ssz_bytes(&ssz_bytes).unwrap();
+            let block: BeaconBlock = decode_ssz_bytes(ssz_bytes, input_path)?;
             let _ = state.process_block_header(&block);
         }
         OperationInput::SignedBLSToExecutionChange(ssz_bytes) => {
-            let bls_change: SignedBLSToExecutionChange = from_ssz_bytes(&ssz_bytes).unwrap();
+            let bls_change: SignedBLSToExecutionChange = decode_ssz_bytes(ssz_bytes, input_path)?;
             let _ = state.process_bls_to_execution_change(&bls_change);
         }
         OperationInput::Deposit(ssz_bytes) => {
-            let deposit: Deposit = from_ssz_bytes(&ssz_bytes).unwrap();
+            let deposit: Deposit = decode_ssz_bytes(ssz_bytes, input_path)?;
             let _ = state.process_deposit(&deposit);
         }
         OperationInput::BeaconBlockBody(_ssz_bytes) => {
-            panic!("Not implemented");
-            // let block_body: BeaconBlockBody = from_ssz_bytes(&ssz_bytes).unwrap();
+            unreachable!("the guest rejects execution_payload cases before a root is compared");
+            // let block_body: BeaconBlockBody = decode_ssz_bytes(ssz_bytes, input_path)?;
             // let _ = state.process_execution_payload(&block_body);
         }
         OperationInput::ProposerSlashing(ssz_bytes) => {
-            let proposer_slashing: ProposerSlashing = from_ssz_bytes(&ssz_bytes).unwrap();
+            let proposer_slashing: ProposerSlashing = decode_ssz_bytes(ssz_bytes, input_path)?;
             let _ = state.process_proposer_slashing(&proposer_slashing);
         }
         OperationInput::SyncAggregate(ssz_bytes) => {
-            let sync_aggregate: SyncAggregate = from_ssz_bytes(&ssz_bytes).unwrap();
+            let sync_aggregate: SyncAggregate = decode_ssz_bytes(ssz_bytes, input_path)?;
             let _ = state.process_sync_aggregate(&sync_aggregate);
         }
         OperationInput::SignedVoluntaryExit(ssz_bytes) => {
-            let voluntary_exit: SignedVoluntaryExit = from_ssz_bytes(&ssz_bytes).unwrap();
+            let voluntary_exit: SignedVoluntaryExit = decode_ssz_bytes(ssz_bytes, input_path)?;
             let _ = state.process_voluntary_exit(&voluntary_exit);
         }
         OperationInput::ExecutionPayload(ssz_bytes) => {
-            let execution_payload: ExecutionPayload = from_ssz_bytes(&ssz_bytes).unwrap();
+            let execution_payload: ExecutionPayload = decode_ssz_bytes(ssz_bytes, input_path)?;
             let _ = state.process_withdrawals(&execution_payload);
         }
         OperationInput::EpochProcessing(epoch_type) => {
@@ -351,18 +1517,57 @@ fn assert_state_root_matches_recompute(
                 EpochProcessingType::SyncCommitteeUpdates => {
                     let _ = state.process_sync_committee_updates();
                 }
+                EpochProcessingType::Full => {
+                    let _ = state.process_epoch();
+                }
+                EpochProcessingType::ProposerLookahead => {
+                    unreachable!("the host CLI only runs proposer_lookahead on Fulu states")
+                }
             }
         }
         OperationInput::ProcessSlot => {
             let _ = state.process_slot();
         }
+        OperationInput::Noop => {}
+        OperationInput::SignedBeaconBlock(ssz_bytes) => {
+            let signed_block: SignedBeaconBlock = decode_ssz_bytes(ssz_bytes, input_path)?;
+            let _ = state.state_transition(&signed_block, true);
+        }
+        OperationInput::UpgradeToFork
+        | OperationInput::PreForkSignedBeaconBlock(_)
+        | OperationInput::UpgradeAtEpoch(_) => {
+            unreachable!("cases starting from the previous fork are never recomputed")
+        }
+        OperationInput::RewardsDeltas => {
+            unreachable!("rewards cases are checked against their delta files")
+        }
+        OperationInput::SszStatic(..) => {
+            unreachable!("ssz_static cases are checked against their roots.yaml")
+        }
+        OperationInput::Shuffle { .. } => {
+            unreachable!("shuffling cases are checked against their mapping.yaml")
+        }
+        OperationInput::MerkleProof { .. } => {
+            unreachable!("single_merkle_proof cases are checked against their proof.yaml")
+        }
+        OperationInput::StateQuery { .. } => {
+            unreachable!("state queries are only run by the query subcommand")
+        }
+        OperationInput::ForkChoiceAnchor(_)
+        | OperationInput::OnTick(_)
+        | OperationInput::OnBlock { .. }
+        | OperationInput::OnAttestation { .. }
+        | OperationInput::OnAttesterSlashing { .. }
+        | OperationInput::ForkChoiceChecks => {
+            unreachable!("fork choice cases are checked against their steps.yaml")
+        }
+        OperationInput::LightClientBootstrap { .. }
+        | OperationInput::LightClientUpdate { .. }
+        | OperationInput::LightClientForceUpdate(_)
+        | OperationInput::RankedLightClientUpdate(_) => {
+            unreachable!("light client cases are checked against their steps.yaml")
+        }
     }
 
-    let recomputed_state_root = state.tree_hash_root();
-
-    println!("recomputed_state_root: {}", recomputed_state_root);
-    println!("new_state_root: {}", new_state_root);
-
-    assert_eq!(*new_state_root, recomputed_state_root);
-    info!("Execution is correct! State roots match host's recomputed state root.");
+    Ok(())
 }
@@ -0,0 +1,91 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+use tracing::error;
+
+/// Outcome of a single consensus-spec-tests case.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseStatus {
+    Ok,
+    LoadError,
+    InvalidInput,
+    MalformedOutput,
+    RootMismatch,
+    GuestPanic,
+    /// The guest exited early on request (`--stop-after`), so there is no root to check.
+    Stopped,
+}
+
+impl CaseStatus {
+    pub fn is_failure(&self) -> bool {
+        !matches!(self, CaseStatus::Ok | CaseStatus::Stopped)
+    }
+}
+
+/// One line of the results file.
+#[derive(Serialize, Debug)]
+pub struct CaseResult {
+    pub operation: String,
+    pub test_case: String,
+    pub status: CaseStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycles: Option<u64>,
+    /// Number of operations applied in one guest run, when more than one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycles_per_operation: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Appends [`CaseResult`]s to a JSON-lines file so a long run keeps its results even if it is
+/// interrupted.
+pub struct ResultsWriter {
+    file: Option<File>,
+    failed: usize,
+}
+
+impl ResultsWriter {
+    pub fn new(path: Option<&Path>) -> std::io::Result<Self> {
+        let file = match path {
+            Some(path) => {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                Some(OpenOptions::new().create(true).append(true).open(path)?)
+            }
+            None => None,
+        };
+
+        Ok(Self { file, failed: 0 })
+    }
+
+    pub fn record(&mut self, result: CaseResult) {
+        if result.status.is_failure() {
+            self.failed += 1;
+            error!(
+                "[{}] {} failed ({:?}): {}",
+                result.operation,
+                result.test_case,
+                result.status,
+                result.error.as_deref().unwrap_or("")
+            );
+        }
+
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+        let line = serde_json::to_string(&result).expect("CaseResult is always serializable");
+        if let Err(err) = writeln!(file, "{line}") {
+            error!("Could not write results file: {err}");
+        }
+    }
+
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+}
@@ -0,0 +1,58 @@
+#!/bin/bash
+
+# Compares process_epoch with the sum of its steps run on the same pre-states, after subtracting
+# the no-op baseline from every run.
+# Usage: epoch_breakdown.sh "<steps>" [run tag]
+STEPS=$1
+RUN_TAG=$2
+
+FULL_FILE="results/results_process_epoch$RUN_TAG.jsonl"
+NOOP_FILE="results/results_justification_and_finalization_epoch_noop$RUN_TAG.jsonl"
+OUTPUT_FILE="summaries/epoch_breakdown$RUN_TAG.md"
+
+STEP_FILES=()
+for step in $STEPS; do
+    STEP_FILES+=("results/results_${step}_epoch_states$RUN_TAG.jsonl")
+done
+
+# Table Header
+echo '| Test Case | No-op Cycles | process_epoch Cycles | Sum of Step Cycles | Steps | Difference |' > $OUTPUT_FILE
+echo '|-----------|--------------|----------------------|--------------------|-------|------------|' >> $OUTPUT_FILE
+
+awk -v full_file="$FULL_FILE" -v noop_file="$NOOP_FILE" '
+function field(line, name,    value) {
+    if (match(line, "\"" name "\":(\"[^\"]*\"|[0-9]+)")) {
+        value = substr(line, RSTART + length(name) + 3, RLENGTH - length(name) - 3);
+        gsub(/"/, "", value);
+        return value;
+    }
+    return "";
+}
+
+{
+    test_case = field($0, "test_case");
+    cycles = field($0, "cycles");
+    if (cycles == "") {
+        next;
+    }
+    if (FILENAME == full_file) {
+        full[test_case] = cycles;
+    } else if (FILENAME == noop_file) {
+        noop[test_case] = cycles;
+    } else {
+        steps_sum[test_case] += cycles;
+        steps[test_case] += 1;
+    }
+}
+
+END {
+    for (test_case in full) {
+        if (!(test_case in noop) || !(test_case in steps)) {
+            continue;
+        }
+        full_net = full[test_case] - noop[test_case];
+        steps_net = steps_sum[test_case] - steps[test_case] * noop[test_case];
+        printf "%s | %d | %d | %d | %d | %d |\n", test_case, noop[test_case], full_net, steps_net, steps[test_case], full_net - steps_net >> "'$OUTPUT_FILE'"
+    }
+}
+' "$FULL_FILE" "$NOOP_FILE" "${STEP_FILES[@]}"
@@ -0,0 +1,32 @@
+#!/bin/bash
+
+# Lists the cycles of every block of a block chain case, and the cumulative cycles up to it,
+# from the host's block prefix runs (--block-prefixes).
+OPERATION=$1
+
+LOG_FILE="logs/execution_$OPERATION.log"
+OUTPUT_FILE="summaries/blocks_$OPERATION.md"
+
+# Table Header
+echo '| Operation | Test Case | Block | Cycles | Cumulative Cycles |' > $OUTPUT_FILE
+echo '|-----------|-----------|-------|--------|-------------------|' >> $OUTPUT_FILE
+
+awk '
+BEGIN {
+    op = "";
+    test_case = "";
+}
+
+/\[.*\] Test case:/ {
+    op = $4;
+    gsub(/[\[\]]/, "", op)
+    test_case = $NF;
+}
+
+# Block <index>: <cycles> cycles, <cumulative> cumulative
+/Block [0-9]+: [0-9]+ cycles, [0-9]+ cumulative/ {
+    block = $(NF-4);
+    sub(/:/, "", block);
+    printf "%s | %s | %d | %d | %d |\n", op, test_case, block, $(NF-3), $(NF-1) >> "'$OUTPUT_FILE'"
+}
+' $LOG_FILE
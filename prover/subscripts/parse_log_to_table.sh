@@ -6,8 +6,8 @@ LOG_FILE="logs/execution_$OPERATION.log"
 OUTPUT_FILE="summaries/summary_$OPERATION.md"
 
 # Table Header
-echo '| Operation | Test Case | Read Pre-State SSZ | Deserialize Pre-State SSZ | Read Operation Input | Process | Merkleize | Commit | Total Cycles | Execution Time |' > $OUTPUT_FILE
-echo '|-----------|-----------|--------------------|---------------------------|----------------------|---------|-----------|--------|--------------|----------------|' >> $OUTPUT_FILE
+echo '| Operation | Test Case | Read Pre-State SSZ | Decompress Pre-State SSZ | Deserialize Pre-State SSZ | Read Operation Input | Process | Merkleize | Commit | Total Cycles | Execution Time |' > $OUTPUT_FILE
+echo '|-----------|-----------|--------------------|--------------------------|---------------------------|----------------------|---------|-----------|--------|--------------|----------------|' >> $OUTPUT_FILE
 
 awk '
 BEGIN {
@@ -15,6 +15,8 @@ BEGIN {
     test_case = "";
     read_pre_state_ssz_start = 0;
     read_pre_state_ssz_end = 0;
+    decompress_pre_state_ssz_start = 0;
+    decompress_pre_state_ssz_end = 0;
     deserialize_pre_state_ssz_start = 0;
     deserialize_pre_state_ssz_end = 0;
     read_operation_input_start = 0;
@@ -42,11 +44,19 @@ BEGIN {
     read_pre_state_ssz_end = $NF;
 }
 
-/deserialize-ream_consensus::electra::beacon_state::BeaconState:start:/ {
+/decompress-pre-state-ssz:start:/ {
+    decompress_pre_state_ssz_start = $NF;
+}
+
+/decompress-pre-state-ssz:end:/ {
+    decompress_pre_state_ssz_end = $NF;
+}
+
+/deserialize-(ream_consensus::electra::beacon_state::BeaconState|LazyBeaconState):start:/ {
     deserialize_pre_state_ssz_start = $NF;
 }
 
-/deserialize-ream_consensus::electra::beacon_state::BeaconState:end:/ {
+/deserialize-(ream_consensus::electra::beacon_state::BeaconState|LazyBeaconState):end:/ {
     deserialize_pre_state_ssz_end = $NF;
 }
 
@@ -87,13 +97,15 @@ BEGIN {
 }
 
 /----- Cycle Tracker End -----/ {
-    printf "%s | %s | %d | %d | %d | %d | %d | %d | %d | %s |\n", op, test_case, read_pre_state_ssz_end-read_pre_state_ssz_start, deserialize_pre_state_ssz_end-deserialize_pre_state_ssz_start, read_operation_input_end-read_operation_input_start, process_operation_end-process_operation_start, merkleize_operation_end-merkleize_operation_start, commit_end-commit_start, commit_end, execution_time >> "'$OUTPUT_FILE'"
+    printf "%s | %s | %d | %d | %d | %d | %d | %d | %d | %d | %s |\n", op, test_case, read_pre_state_ssz_end-read_pre_state_ssz_start, decompress_pre_state_ssz_end-decompress_pre_state_ssz_start, deserialize_pre_state_ssz_end-deserialize_pre_state_ssz_start, read_operation_input_end-read_operation_input_start, process_operation_end-process_operation_start, merkleize_operation_end-merkleize_operation_start, commit_end-commit_start, commit_end, execution_time >> "'$OUTPUT_FILE'"
 
     # Re-initialize for next log
     op = "";
     test_case = "";
     read_pre_state_ssz_start = 0;
     read_pre_state_ssz_end = 0;
+    decompress_pre_state_ssz_start = 0;
+    decompress_pre_state_ssz_end = 0;
     deserialize_pre_state_ssz_start = 0;
     deserialize_pre_state_ssz_end = 0;
     read_operation_input_start = 0;
@@ -0,0 +1,48 @@
+#!/bin/bash
+
+# Averages the decode and hash cycles of the ssz_static cases per container type, from the guest's
+# deserialize and merkleize-operation markers.
+OPERATION=$1
+
+LOG_FILE="logs/execution_$OPERATION.log"
+OUTPUT_FILE="summaries/ssz_static_$OPERATION.md"
+
+# Table Header
+echo '| Type | Cases | Avg Decode Cycles | Avg Hash Cycles | Max Decode Cycles | Max Hash Cycles |' > $OUTPUT_FILE
+echo '|------|-------|-------------------|-----------------|-------------------|-----------------|' >> $OUTPUT_FILE
+
+awk '
+# Test case: <Type>/<suite>/<case>
+/\[.*\] Test case:/ {
+    split($NF, parts, "/");
+    type = parts[1];
+    cases[type]++;
+}
+
+# deserialize-<type_name>:start: <cycles>
+/deserialize-.*:start:/ {
+    decode_start = $NF;
+}
+
+/deserialize-.*:end:/ {
+    cycles = $NF - decode_start;
+    decode_total[type] += cycles;
+    if (cycles > decode_max[type]) decode_max[type] = cycles;
+}
+
+/merkleize-operation:start:/ {
+    hash_start = $NF;
+}
+
+/merkleize-operation:end:/ {
+    cycles = $NF - hash_start;
+    hash_total[type] += cycles;
+    if (cycles > hash_max[type]) hash_max[type] = cycles;
+}
+
+END {
+    for (type in cases) {
+        printf "| %s | %d | %d | %d | %d | %d |\n", type, cases[type], decode_total[type] / cases[type], hash_total[type] / cases[type], decode_max[type], hash_max[type];
+    }
+}
+' $LOG_FILE | sort >> $OUTPUT_FILE
@@ -0,0 +1,52 @@
+#!/bin/bash
+
+# Lists the cycles of no-op runs stopped after the read phase for each test case: reading raw SSZ
+# pre-states, and reading snappy pre-states and decompressing them in the guest.
+# Usage: read_phase.sh <operation> [run tag]
+OPERATION=$1
+RUN_TAG=$2
+
+RAW_FILE="results/results_${OPERATION}_read_raw$RUN_TAG.jsonl"
+SNAPPY_FILE="results/results_${OPERATION}_read_snappy$RUN_TAG.jsonl"
+OUTPUT_FILE="summaries/read_$OPERATION$RUN_TAG.md"
+
+# Table Header
+echo '| Test Case | Raw Read Cycles | Snappy Read + Decompress Cycles | Difference |' > $OUTPUT_FILE
+echo '|-----------|-----------------|---------------------------------|------------|' >> $OUTPUT_FILE
+
+awk '
+function field(line, name,    value) {
+    if (match(line, "\"" name "\":(\"[^\"]*\"|[0-9]+)")) {
+        value = substr(line, RSTART + length(name) + 3, RLENGTH - length(name) - 3);
+        gsub(/"/, "", value);
+        return value;
+    }
+    return "";
+}
+
+# The raw file is read first
+FNR == NR {
+    if (field($0, "cycles") != "") {
+        raw[field($0, "test_case")] = field($0, "cycles");
+    }
+    next;
+}
+
+{
+    test_case = field($0, "test_case");
+    cycles = field($0, "cycles");
+    if (cycles == "" || !(test_case in raw)) {
+        next;
+    }
+    raw_total += raw[test_case];
+    snappy_total += cycles;
+    count++;
+    printf "%s | %d | %d | %d |\n", test_case, raw[test_case], cycles, cycles - raw[test_case] >> "'$OUTPUT_FILE'"
+}
+
+END {
+    if (count > 0) {
+        printf "**Average** | %d | %d | %d |\n", raw_total / count, snappy_total / count, (snappy_total - raw_total) / count >> "'$OUTPUT_FILE'"
+    }
+}
+' $RAW_FILE $SNAPPY_FILE
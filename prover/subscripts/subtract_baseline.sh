@@ -0,0 +1,42 @@
+#!/bin/bash
+
+# Subtracts the no-op baseline of each test case from the operation's total cycles.
+# Usage: subtract_baseline.sh <operation> [run tag]
+OPERATION=$1
+RUN_TAG=$2
+
+RESULTS_FILE="results/results_$OPERATION$RUN_TAG.jsonl"
+BASELINE_FILE="results/results_${OPERATION}_noop_merkleize$RUN_TAG.jsonl"
+OUTPUT_FILE="summaries/overhead_$OPERATION$RUN_TAG.md"
+
+# Table Header
+echo '| Operation | Test Case | Total Cycles | No-op Cycles | Operation Cycles |' > $OUTPUT_FILE
+echo '|-----------|-----------|--------------|--------------|------------------|' >> $OUTPUT_FILE
+
+awk '
+function field(line, name,    value) {
+    if (match(line, "\"" name "\":(\"[^\"]*\"|[0-9]+)")) {
+        value = substr(line, RSTART + length(name) + 3, RLENGTH - length(name) - 3);
+        gsub(/"/, "", value);
+        return value;
+    }
+    return "";
+}
+
+# The baseline file is read first
+FNR == NR {
+    if (field($0, "cycles") != "") {
+        baseline[field($0, "test_case")] = field($0, "cycles");
+    }
+    next;
+}
+
+{
+    test_case = field($0, "test_case");
+    cycles = field($0, "cycles");
+    if (cycles == "" || !(test_case in baseline)) {
+        next;
+    }
+    printf "%s | %s | %d | %d | %d |\n", field($0, "operation"), test_case, cycles, baseline[test_case], cycles - baseline[test_case] >> "'$OUTPUT_FILE'"
+}
+' $BASELINE_FILE $RESULTS_FILE